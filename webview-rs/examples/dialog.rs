@@ -12,19 +12,16 @@ fn main() -> WVResult {
         .resizable(true)
         .debug(true)
         .user_data(())
-        .invoke_handler(|webview, arg| {
-            match arg {
-                "open" => match webview.dialog().open_file("Please choose a file...", "")? {
-                    Some(path) => webview.dialog().info("File chosen", path.to_string_lossy()),
-                    None => webview
-                        .dialog()
-                        .warning("Warning", "You didn't choose a file."),
-                }?,
-                "exit" => {
-                    webview.terminate();
-                }
-                _ => unimplemented!(),
-            };
+        .command("open", |webview, ()| {
+            match webview.dialog().open_file("Please choose a file...", "")? {
+                Some(path) => webview.dialog().info("File chosen", path.to_string_lossy()),
+                None => webview
+                    .dialog()
+                    .warning("Warning", "You didn't choose a file."),
+            }
+        })
+        .command("exit", |webview, ()| {
+            webview.terminate();
             Ok(())
         })
         .build()?;
@@ -36,8 +33,8 @@ const HTML: &str = r#"
 <!doctype html>
 <html>
 	<body>
-		<button onclick="external.invoke('open')">Open</button>
-		<button onclick="external.invoke('exit')">Exit</button>
+		<button onclick="rust.call('open')">Open</button>
+		<button onclick="rust.call('exit')">Exit</button>
 	</body>
 </html>
 "#;
@@ -20,34 +20,31 @@ fn main() {
         .resizable(true)
         .debug(true)
         .user_data(0)
-        .invoke_handler(|webview, arg| {
-            match arg {
-                "reset" => {
-                    let mut counter = counter.lock().unwrap();
-                    let user_data = webview.user_data();
-                    let mut lock = user_data.write().unwrap();
-                    *lock += 10;
+        .command("reset", |webview, ()| {
+            let mut counter = counter.lock().unwrap();
+            let user_data = webview.user_data();
+            let mut lock = user_data.write().unwrap();
+            *lock += 10;
 
-                    drop(lock);
-                    *counter = 0;
-                    render(webview, *counter)?;
-                }
-                "exit" => {
-                    webview.terminate();
-                }
-                _ => unimplemented!(),
-            };
+            drop(lock);
+            *counter = 0;
+            render(webview, *counter)
+        })
+        .command("exit", |webview, ()| {
+            webview.terminate();
             Ok(())
         })
         .build()
         .unwrap();
 
     let handle = webview.handle();
+    let mut ticks = handle.channel("ticks");
     thread::spawn(move || loop {
         {
             let mut counter = counter_inner.lock().unwrap();
             *counter += 1;
             let count = *counter;
+            ticks.send(count).unwrap();
             handle
                 .dispatch(move |webview| {
                     let user_data = webview.user_data();
@@ -67,7 +64,7 @@ fn main() {
 fn render(webview: &mut WebView<i32>, counter: u32) -> WVResult {
     let data = *webview.user_data().read().unwrap();
     println!("counter: {}, userdata: {}", counter, data);
-    webview.eval(&format!("updateTicks({}, {})", counter, data))
+    webview.eval(&js!("updateTicks({}, {})", counter, data)?)
 }
 
 const HTML: &str = r#"
@@ -75,12 +72,26 @@ const HTML: &str = r#"
 <html>
 	<body>
 		<p id="ticks"></p>
-		<button onclick="external.invoke('reset')">reset</button>
-		<button onclick="external.invoke('exit')">exit</button>
+		<button onclick="rust.call('reset')">reset</button>
+		<button onclick="rust.call('exit')">exit</button>
 		<script type="text/javascript">
+			var userdata = 0;
+
 			function updateTicks(n, u) {
+				userdata = u;
 				document.getElementById('ticks').innerHTML = 'ticks ' + n + '<br>' + 'userdata ' + u;
 			}
+
+			var ticks = rust.channel('ticks');
+
+			(function next() {
+				ticks.next().then(function(tick) {
+					if (!tick.done) {
+						updateTicks(tick.value, userdata);
+						next();
+					}
+				});
+			})();
 		</script>
 	</body>
 </html>
@@ -38,25 +38,27 @@ fn main() {
         .resizable(false)
         .debug(true)
         .user_data(vec![])
-        .invoke_handler(|webview, arg| {
-            use Cmd::*;
-
-            let tasks_len = {
-                let locked_tasks = webview.user_data();
-                let mut tasks = locked_tasks.write().unwrap();
-
-                match serde_json::from_str(arg).unwrap() {
-                    Init => (),
-                    Log { text } => println!("{}", text),
-                    AddTask { name } => tasks.push(Task { name, done: false }),
-                    MarkTask { index, done } => tasks[index].done = done,
-                    ClearDoneTasks => tasks.retain(|t| !t.done),
-                }
-
-                tasks.len()
-            };
-
-            webview.set_title(&format!("Rust Todo App ({} Tasks)", tasks_len))?;
+        .command("init", |webview, ()| render(webview))
+        .command("log", |_webview, Log { text }| {
+            println!("{}", text);
+            Ok(())
+        })
+        .command("addTask", |webview, AddTask { name }| {
+            webview.user_data().write().unwrap().push(Task { name, done: false });
+            render(webview)
+        })
+        .command("markTask", |webview, MarkTask { index, done }| {
+            webview
+                .user_data()
+                .write()
+                .unwrap()
+                .get_mut(index)
+                .ok_or_else(|| Error::custom(format!("No task at index {}", index)))?
+                .done = done;
+            render(webview)
+        })
+        .command("clearDoneTasks", |webview, ()| {
+            webview.user_data().write().unwrap().retain(|t| !t.done);
             render(webview)
         })
         .build()
@@ -67,13 +69,14 @@ fn main() {
 }
 
 fn render(webview: &mut WebView<Vec<Task>>) -> WVResult {
-    let render_tasks = {
+    let (tasks_len, tasks) = {
         let lock = webview.user_data();
         let tasks = lock.read().unwrap();
         println!("{:#?}", tasks);
-        format!("rpc.render({})", serde_json::to_string(&*tasks).unwrap())
+        (tasks.len(), serde_json::to_value(&*tasks).unwrap())
     };
-    webview.eval(&render_tasks)
+    webview.set_title(&format!("Rust Todo App ({} Tasks)", tasks_len))?;
+    webview.emit("render", tasks)
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -83,13 +86,19 @@ struct Task {
 }
 
 #[derive(Deserialize)]
-#[serde(tag = "cmd", rename_all = "camelCase")]
-pub enum Cmd {
-    Init,
-    Log { text: String },
-    AddTask { name: String },
-    MarkTask { index: usize, done: bool },
-    ClearDoneTasks,
+struct Log {
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct AddTask {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct MarkTask {
+    index: usize,
+    done: bool,
 }
 
 fn inline_style(s: &str) -> String {
@@ -37,8 +37,8 @@ function UI(items) {
 var element;
 var oldNode;
 var rpc = {
-  invoke : function(arg) { window.external.invoke(JSON.stringify(arg)); },
-  init : function() { rpc.invoke({cmd : 'init'}); },
+  invoke : function(cmd, args) { return rust.call(cmd, args); },
+  init : function() { rpc.invoke('init'); },
   log : function() {
     var s = '';
     for (var i = 0; i < arguments.length; i++) {
@@ -47,16 +47,17 @@ var rpc = {
       }
       s = s + JSON.stringify(arguments[i]);
     }
-    rpc.invoke({cmd : 'log', text : s});
+    rpc.invoke('log', {text : s});
   },
-  addTask : function(name) { rpc.invoke({cmd : 'addTask', name : name}); },
-  clearDoneTasks : function() { rpc.invoke({cmd : 'clearDoneTasks'}); },
+  addTask : function(name) { rpc.invoke('addTask', {name : name}); },
+  clearDoneTasks : function() { rpc.invoke('clearDoneTasks'); },
   markTask : function(index, done) {
-    rpc.invoke({cmd : 'markTask', index : index, done : done});
+    rpc.invoke('markTask', {index : index, done : done});
   },
   render : function(items) {
     return element = picodom.patch(oldNode, (oldNode = UI(items)), element);
   },
 };
 
+rust.on('render', rpc.render);
 window.onload = function() { rpc.init(); };
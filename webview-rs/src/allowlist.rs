@@ -0,0 +1,226 @@
+/// A message rejected by the allowlist, passed to the hook set with
+/// [`WebViewBuilder::on_violation`].
+///
+/// [`WebViewBuilder::on_violation`]: struct.WebViewBuilder.html#method.on_violation
+#[derive(Debug)]
+pub struct Violation {
+    /// URL of the page which sent the message, if known.
+    pub url: Option<String>,
+    /// The command called, or `None` if the message was not a call made with `rust.call`.
+    pub command: Option<String>,
+    /// The message as received.
+    pub message: String,
+}
+
+pub type ViolationHook<'a> = Box<dyn FnMut(&Violation) + 'a>;
+
+/// What a message from JavaScript asks for.
+#[derive(Clone, Copy, Debug)]
+pub enum Access<'b> {
+    /// A call to the command, made with `rust.call`.
+    Call(&'b str),
+    /// Any other message sent by the bridge script, e.g. the result of `eval_with_result`.
+    Bridge,
+    /// A message passed to `external.invoke` directly, only seen by the invoke handler.
+    Raw,
+}
+
+/// Pages allowed to send messages to Rust, by URL pattern, and the commands they may call.
+///
+/// An empty allowlist allows everything.
+#[derive(Default)]
+pub struct Allowlist<'a> {
+    rules: Vec<(String, Vec<String>)>,
+    hook: Option<ViolationHook<'a>>,
+}
+
+impl<'a> Allowlist<'a> {
+    /// Creates an empty allowlist.
+    pub fn new() -> Self {
+        Allowlist::default()
+    }
+
+    /// Allows pages whose URL matches `pattern` to call `commands`. See
+    /// [`WebViewBuilder::allow`].
+    ///
+    /// [`WebViewBuilder::allow`]: struct.WebViewBuilder.html#method.allow
+    pub fn allow(&mut self, pattern: &str, commands: &[&str]) {
+        self.rules.push((pattern.to_string(), commands.iter().map(|c| c.to_string()).collect()));
+    }
+
+    /// Sets `hook` to be called with every rejected message.
+    pub fn on_violation<H: FnMut(&Violation) + 'a>(&mut self, hook: H) {
+        self.hook = Some(Box::new(hook));
+    }
+
+    /// Returns `true` if no pages have been allowed, in which case every message is.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Returns `true` if a page at `url` may send a message asking for `access`.
+    pub fn permits(&self, url: Option<&str>, access: Access) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+
+        let url = match url {
+            Some(url) => url,
+            None => return false,
+        };
+
+        self.rules
+            .iter()
+            .filter(|(pattern, _)| pattern == "*" || url_match(pattern, url))
+            .any(|(_, commands)| match access {
+                Access::Bridge => true,
+                Access::Call(command) => commands.iter().any(|c| c == "*" || c == command),
+                Access::Raw => commands.iter().any(|c| c == "*"),
+            })
+    }
+
+    /// Like [`permits`], additionally reporting a rejected `message` to the hook.
+    ///
+    /// [`permits`]: struct.Allowlist.html#method.permits
+    pub(crate) fn check(&mut self, url: Option<&str>, access: Access, message: &str) -> bool {
+        if self.permits(url, access) {
+            return true;
+        }
+
+        if let Some(hook) = self.hook.as_mut() {
+            hook(&Violation {
+                url: url.map(str::to_string),
+                command: match access {
+                    Access::Call(command) => Some(command.to_string()),
+                    _ => None,
+                },
+                message: message.to_string(),
+            });
+        }
+
+        false
+    }
+}
+
+/// Matches the URL `url` against `pattern`, in which `*` matches any sequence of characters within
+/// the origin, or within the rest of the URL, so that no URL of another host matches.
+fn url_match(pattern: &str, url: &str) -> bool {
+    let (pattern_origin, pattern_rest) = split_origin(pattern);
+    let (origin, rest) = split_origin(url);
+
+    // Without the user info, a pattern could only match the host
+    let pattern_parts: Vec<&str> = pattern_origin.split('@').collect();
+    let parts: Vec<&str> = origin.split('@').collect();
+
+    pattern_parts.len() == parts.len()
+        && pattern_parts.iter().zip(&parts).all(|(pattern, part)| glob_match(pattern, part))
+        && glob_match(pattern_rest, rest)
+}
+
+/// Splits `url` after its scheme and authority, before the first `/`, `?` or `#` following `://`.
+/// URLs without an authority, such as `data:` URLs, have no origin to split.
+fn split_origin(url: &str) -> (&str, &str) {
+    let start = match url.find("://") {
+        Some(i) if url[..i].bytes().all(|c| c.is_ascii_alphanumeric() || b"+-.".contains(&c)) => i + 3,
+        _ => return ("", url),
+    };
+    let end = url[start..].find(&['/', '?', '#'][..]).map(|i| start + i).unwrap_or(url.len());
+
+    (&url[..end], &url[end..])
+}
+
+/// Matches `text` against `pattern`, in which `*` matches any sequence of characters.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text) = (pattern.as_bytes(), text.as_bytes());
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text it has matched up to
+    let mut backtrack = None;
+
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            p += 1;
+            backtrack = Some((p, t));
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("https://example.com/*", "https://example.com/"));
+        assert!(glob_match("https://example.com/*", "https://example.com/a/b?c"));
+        assert!(glob_match("https://*.example.com/*", "https://app.example.com/"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("data:*", "data:text/html,hello"));
+        assert!(!glob_match("https://example.com/*", "https://example.com.evil.org/"));
+        assert!(!glob_match("https://*.example.com/*", "https://example.com/"));
+        assert!(!glob_match("https://example.com", "https://example.com/"));
+
+        assert!(url_match("https://example.com/*", "https://example.com/a/b?c"));
+        assert!(url_match("https://*.example.com/*", "https://app.example.com/"));
+        assert!(url_match("https://*.example.com/*", "https://a.b.example.com/?q#f"));
+        assert!(url_match("https://example.com:*/*", "https://example.com:8080/"));
+        assert!(url_match("data:*", "data:text/html,https://evil.org/"));
+        assert!(!url_match("https://*.example.com/*", "https://evil.org/x.example.com/"));
+        assert!(!url_match("https://*.example.com/*", "https://evil.org/?.example.com/"));
+        assert!(!url_match("https://*.example.com/*", "https://evil.org#.example.com/"));
+        assert!(!url_match("https://*.example.com/*", "https://x.example.com@evil.org/"));
+        assert!(!url_match("https://example.com/*", "https://example.com.evil.org/"));
+        assert!(!url_match("https://example.com", "https://example.com/"));
+        assert!(!url_match("https://*", "https://evil.org/"));
+    }
+
+    #[test]
+    fn test_permits() {
+        let mut allowlist = Allowlist::new();
+
+        assert!(allowlist.permits(None, Access::Raw));
+
+        allowlist.allow("https://app.example.com/*", &["addTask"]);
+        allowlist.allow("data:*", &["*"]);
+
+        let app = Some("https://app.example.com/index.html");
+        let evil = Some("https://evil.org/");
+
+        assert!(allowlist.permits(app, Access::Call("addTask")));
+        assert!(allowlist.permits(app, Access::Bridge));
+        assert!(!allowlist.permits(app, Access::Call("deleteAll")));
+        assert!(!allowlist.permits(app, Access::Raw));
+        assert!(allowlist.permits(Some("data:text/html,"), Access::Raw));
+        assert!(!allowlist.permits(evil, Access::Call("addTask")));
+        assert!(!allowlist.permits(evil, Access::Bridge));
+        assert!(!allowlist.permits(None, Access::Bridge));
+    }
+
+    #[test]
+    fn test_violation_hook() {
+        let mut violations = Vec::new();
+
+        {
+            let mut allowlist = Allowlist::new();
+
+            allowlist.allow("https://app.example.com/*", &["addTask"]);
+            allowlist.on_violation(|violation: &Violation| violations.push(violation.command.clone()));
+
+            assert!(allowlist.check(Some("https://app.example.com/"), Access::Call("addTask"), "{}"));
+            assert!(!allowlist.check(Some("https://app.example.com/"), Access::Call("deleteAll"), "{}"));
+            assert!(!allowlist.check(Some("https://evil.org/"), Access::Raw, "exit"));
+        }
+
+        assert_eq!(vec![Some("deleteAll".to_string()), None], violations);
+    }
+}
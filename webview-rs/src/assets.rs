@@ -0,0 +1,248 @@
+use crate::error::WVResult;
+use crate::mime;
+use crate::protocol::{
+    ProtocolRequest,
+    Response,
+};
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+/// The URI scheme [`Assets`] and [`Content::Dir`] are served through by default.
+pub(crate) const SCHEME: &str = "app";
+
+/// Files embedded in the binary, served to the [`WebView`] through a custom URI scheme. Created
+/// with the [`assets!`] macro and loaded with [`Content::assets`].
+///
+/// Pages are loaded from `app://localhost/` unless another scheme is set, so that relative URLs
+/// and `fetch` resolve to other assets, each served with the MIME type of its extension and
+/// support for `Range` requests.
+///
+/// Custom URI schemes are only supported by WebKitGTK. Elsewhere, building a WebView with assets
+/// fails with [`Error::Unsupported`].
+///
+/// Whole directories are best embedded with [`embed_assets`], from a build script.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[macro_use] extern crate webview;
+/// # use webview::*;
+/// #
+/// # fn run() -> WVResult {
+/// WebViewBuilder::new()
+///     .content(Content::assets(assets!("../tests/fixtures/site" => ["index.html", "app.js", "style.css"])))
+///     .user_data(())
+///     .invoke_handler(|_webview, _arg| Ok(()))
+///     .build()?
+///     .run()
+/// # }
+/// # fn main() {}
+/// ```
+///
+/// [`WebView`]: struct.WebView.html
+/// [`assets!`]: macro.assets.html
+/// [`embed_assets`]: fn.embed_assets.html
+/// [`Content::assets`]: enum.Content.html#method.assets
+/// [`Error::Unsupported`]: enum.Error.html#variant.Unsupported
+#[derive(Clone, Copy, Debug)]
+pub struct Assets {
+    files: &'static [(&'static str, &'static [u8])],
+    pub(crate) scheme: &'static str,
+    pub(crate) index: &'static str,
+}
+
+impl Assets {
+    /// Creates assets from pairs of paths, relative to the root of the frontend, and contents.
+    pub fn new(files: &'static [(&'static str, &'static [u8])]) -> Self {
+        Assets {
+            files,
+            scheme: SCHEME,
+            index: "index.html",
+        }
+    }
+
+    /// Sets the URI scheme the assets are served through, `app` by default.
+    pub fn scheme(mut self, scheme: &'static str) -> Self {
+        self.scheme = scheme;
+        self
+    }
+
+    /// Sets the page first loaded, as well as served for paths ending with `/`, `index.html` by
+    /// default.
+    pub fn index(mut self, path: &'static str) -> Self {
+        self.index = path;
+        self
+    }
+
+    /// Returns the contents of the file at `path`.
+    pub fn get(&self, path: &str) -> Option<&'static [u8]> {
+        let path = path.trim_start_matches('/');
+
+        self.files.iter().find(|(p, _)| p.trim_start_matches('/') == path).map(|(_, data)| *data)
+    }
+
+    /// Returns the URL of the index page.
+    pub fn url(&self) -> String {
+        format!("{}://localhost/{}", self.scheme, self.index.trim_start_matches('/'))
+    }
+
+    /// Answers a request for one of the assets.
+    pub(crate) fn respond(&self, request: &ProtocolRequest) -> Response {
+        let mut path = request.path();
+
+        if path.ends_with('/') {
+            path.push_str(self.index);
+        }
+
+        match self.get(&path) {
+            Some(data) => Response::new(data).mime(mime::guess(&path)),
+            None => Response::not_found(),
+        }
+    }
+}
+
+/// Embeds files into the binary as [`Assets`], given the directory holding them relative to the
+/// current source file, and their paths within it. See [`embed_assets`] to embed a whole
+/// directory instead.
+///
+/// ```no_run
+/// # #[macro_use] extern crate webview;
+/// # fn main() {
+/// let assets = assets!("../tests/fixtures/site" => ["index.html", "app.js", "style.css"]);
+/// # }
+/// ```
+///
+/// [`Assets`]: struct.Assets.html
+/// [`embed_assets`]: fn.embed_assets.html
+#[macro_export]
+macro_rules! assets {
+    ($dir:literal => [$($path:literal),* $(,)?]) => {
+        $crate::Assets::new(&[
+            $(($path, include_bytes!(concat!($dir, "/", $path)) as &'static [u8])),*
+        ])
+    };
+}
+
+/// Embeds every file below `dir` as [`Assets`], when called from a build script.
+///
+/// Writes an expression creating the assets to `assets.rs` in the `OUT_DIR` of the build script,
+/// to be included with `include!(concat!(env!("OUT_DIR"), "/assets.rs"))`, and has Cargo run the
+/// build script again whenever a file below `dir` changes. Requires `webview` as a build
+/// dependency, under that name.
+///
+/// # Example
+///
+/// In the `main` function of `build.rs`:
+///
+/// ```no_run
+/// webview::embed_assets("frontend/dist").unwrap();
+/// ```
+///
+/// Then in the application:
+///
+/// ```text
+/// let content = Content::assets(include!(concat!(env!("OUT_DIR"), "/assets.rs")));
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::Io`] if `dir` cannot be read, or `OUT_DIR` is not set because this is not
+/// called from a build script.
+///
+/// [`Assets`]: struct.Assets.html
+/// [`Error::Io`]: enum.Error.html#variant.Io
+pub fn embed_assets<P: AsRef<Path>>(dir: P) -> WVResult {
+    let out_dir = env::var_os("OUT_DIR")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "OUT_DIR is not set, call embed_assets from a build script"))?;
+    let dir = dir.as_ref().canonicalize()?;
+
+    fs::write(Path::new(&out_dir).join("assets.rs"), generate(&dir)?)?;
+    println!("cargo:rerun-if-changed={}", dir.display());
+
+    Ok(())
+}
+
+/// Returns an expression embedding every file below `root`, in the order of their paths.
+fn generate(root: &Path) -> io::Result<String> {
+    let mut files = Vec::new();
+    let mut code = String::from("::webview::Assets::new(&[\n");
+
+    list(root, &mut files)?;
+    files.sort();
+
+    for file in files {
+        let path = file.strip_prefix(root).unwrap_or(&file).to_string_lossy().replace('\\', "/");
+
+        let _ = writeln!(code, "    ({:?}, include_bytes!({:?}) as &'static [u8]),", path, file.to_string_lossy());
+    }
+
+    code.push_str("])\n");
+    Ok(code)
+}
+
+/// Adds the path of every file below `dir` to `files`.
+fn list(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            list(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_respond() {
+        let assets = Assets::new(&[
+            ("index.html", b"<html>"),
+            ("css/my style.css", b"body {}"),
+            ("docs/index.html", b"docs"),
+        ]);
+        let respond = |uri: &str| {
+            let response = assets.respond(&ProtocolRequest::new("GET", uri));
+            (response.status, response.body.into_owned(), response.mime)
+        };
+
+        assert_eq!("app://localhost/index.html", assets.url());
+        assert_eq!((200, b"<html>".to_vec(), Some("text/html".to_string())), respond("app://localhost/index.html?a=b"));
+        assert_eq!((200, b"<html>".to_vec(), Some("text/html".to_string())), respond("app://localhost"));
+        assert_eq!((200, b"body {}".to_vec(), Some("text/css".to_string())), respond("app://localhost/css/my%20style.css"));
+        assert_eq!((200, b"docs".to_vec(), Some("text/html".to_string())), respond("app://localhost/docs/#intro"));
+        assert_eq!(404, respond("app://localhost/missing.js").0);
+        assert_eq!("ui://localhost/docs/index.html", assets.scheme("ui").index("docs/index.html").url());
+    }
+
+    #[test]
+    fn test_macro() {
+        let assets = assets!("../tests/fixtures/site" => ["index.html", "app.js",]);
+
+        assert_eq!(Some(&include_bytes!("../tests/fixtures/site/app.js")[..]), assets.get("/app.js"));
+    }
+
+    #[test]
+    fn test_generate() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/site").canonicalize().unwrap();
+        let code = generate(&root).unwrap();
+        let entry = |name: &str| {
+            let file = root.join(name).to_string_lossy().into_owned();
+            code.find(&format!("({:?}, include_bytes!({:?}) as &'static [u8]),", name, file)).unwrap()
+        };
+
+        assert!(code.starts_with("::webview::Assets::new(&["));
+        assert!(entry("app.js") < entry("index.html") && entry("index.html") < entry("style.css"));
+    }
+}
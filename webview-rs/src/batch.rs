@@ -0,0 +1,105 @@
+use crate::error::{
+    Error,
+    WVResult,
+};
+use crate::WebView;
+use std::sync::{
+    Arc,
+    Mutex,
+};
+
+/// Scripts queued through a [`Handle`], evaluated by a single dispatched closure.
+///
+/// [`Handle`]: struct.Handle.html
+#[derive(Debug, Default)]
+pub struct Batch {
+    /// Queued scripts in order, with the key they replace earlier scripts of, if any.
+    scripts: Vec<(Option<String>, String)>,
+    /// Whether a closure evaluating the batch has been dispatched and not yet run.
+    scheduled: bool,
+}
+
+impl Batch {
+    /// Queues `js`, first removing any queued script with the same `key`. Returns `true` if the
+    /// batch needs to be scheduled.
+    pub fn push(&mut self, key: Option<String>, js: String) -> bool {
+        if key.is_some() {
+            self.scripts.retain(|(k, _)| *k != key);
+        }
+
+        self.scripts.push((key, js));
+
+        !std::mem::replace(&mut self.scheduled, true)
+    }
+
+    /// Marks the batch as no longer scheduled, e.g. because dispatching it failed.
+    pub fn unschedule(&mut self) {
+        self.scheduled = false;
+    }
+
+    /// Takes the queued scripts, in order.
+    pub fn take(&mut self) -> Vec<String> {
+        self.scheduled = false;
+        self.scripts.drain(..).map(|(_, js)| js).collect()
+    }
+}
+
+/// Evaluates the scripts queued in a batch once run. If it is dropped without running, as the
+/// WebView was, or dispatching it failed, the batch is scheduled again by the next script queued.
+pub struct Flush {
+    batch: Option<Arc<Mutex<Batch>>>,
+}
+
+impl Flush {
+    pub fn new(batch: Arc<Mutex<Batch>>) -> Self {
+        Flush { batch: Some(batch) }
+    }
+
+    pub fn run<T>(mut self, webview: &mut WebView<T>) -> WVResult {
+        let batch = match self.batch.take() {
+            Some(batch) => batch,
+            None => return Ok(()),
+        };
+        let scripts = batch.lock().map_err(|_| Error::Poisoned)?.take();
+        let mut result = Ok(());
+
+        // Each script is evaluated on its own, so that one which throws or does not even parse
+        // does not prevent the others from running
+        for js in scripts {
+            let evaluated = webview.eval(&js);
+
+            if result.is_ok() {
+                result = evaluated;
+            }
+        }
+
+        result
+    }
+}
+
+impl Drop for Flush {
+    fn drop(&mut self) {
+        if let Some(Ok(mut batch)) = self.batch.as_ref().map(|batch| batch.lock()) {
+            batch.unschedule();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_batch() {
+        let mut batch = Batch::default();
+
+        assert!(batch.push(None, "a()".to_string()));
+        assert!(!batch.push(Some("x".to_string()), "x = 1".to_string()));
+        assert!(!batch.push(None, "b()".to_string()));
+        assert!(!batch.push(Some("x".to_string()), "x = 2".to_string()));
+
+        assert_eq!(vec!["a()", "b()", "x = 2"], batch.take());
+        assert!(batch.take().is_empty());
+        assert!(batch.push(None, "c()".to_string()));
+    }
+}
@@ -0,0 +1,464 @@
+(function() {
+  'use strict';
+
+  if (window.rust) {
+    return;
+  }
+
+  // Prefixes every message, so that Rust can tell messages of the top frame, the only one this
+  // script runs in, from those posted by frames within it.
+  var KEY = '__WEBVIEW_KEY__';
+
+  // On WebKitGTK `window.external` is only installed once the first page has
+  // loaded; make it available to page scripts from document start, and keep
+  // it from being replaced by one which does not add the key.
+  if (window.webkit && window.webkit.messageHandlers && window.webkit.messageHandlers.external) {
+    var handler = window.webkit.messageHandlers.external;
+
+    Object.defineProperty(window, 'external', {
+      value: Object.freeze({
+        invoke: function(message) {
+          handler.postMessage(KEY + message);
+        },
+      }),
+    });
+  }
+
+  // Size in UTF-16 code units above which messages to Rust are split into chunks.
+  var CHUNK_SIZE = 512 * 1024;
+
+  // Size in bytes above which Rust drops messages, or null. Calls are rejected here instead, as
+  // Rust would never answer them.
+  var LIMIT = __WEBVIEW_LIMIT__;
+
+  var nextId = 1;
+  var nextChunkId = 1;
+  var pending = {};
+  var listeners = {};
+  var chunks = {};
+  var channels = {};
+  var unclaimed = {};
+  var claims = {};
+
+  function encode(data) {
+    var bytes = data instanceof ArrayBuffer ? new Uint8Array(data) : new Uint8Array(data.buffer, data.byteOffset, data.byteLength);
+    var binary = '';
+
+    for (var i = 0; i < bytes.length; i += 0x8000) {
+      binary += String.fromCharCode.apply(null, bytes.subarray(i, i + 0x8000));
+    }
+
+    return btoa(binary);
+  }
+
+  function decode(base64) {
+    var binary = atob(base64);
+    var bytes = new Uint8Array(binary.length);
+
+    for (var i = 0; i < binary.length; i++) {
+      bytes[i] = binary.charCodeAt(i);
+    }
+
+    return bytes;
+  }
+
+  // Sends binary data as `{ __webviewBytes: base64 }`, deserialized into `webview::Bytes`.
+  function replacer(key, value) {
+    if (value instanceof ArrayBuffer || ArrayBuffer.isView(value)) {
+      return { __webviewBytes: encode(value) };
+    }
+
+    return value;
+  }
+
+  // Turns `webview::Bytes`, serialized as `{ __webviewBytes: base64 }`, into Uint8Arrays.
+  function revive(value) {
+    if (value && typeof value === 'object') {
+      var keys = Object.keys(value);
+
+      if (keys.length === 1 && keys[0] === '__webviewBytes' && typeof value.__webviewBytes === 'string') {
+        return decode(value.__webviewBytes);
+      }
+
+      keys.forEach(function(key) {
+        value[key] = revive(value[key]);
+      });
+    }
+
+    return value;
+  }
+
+  function post(message) {
+    send(JSON.stringify(message, replacer));
+  }
+
+  function send(json) {
+    if (json.length <= CHUNK_SIZE) {
+      window.external.invoke(json);
+      return;
+    }
+
+    var id = nextChunkId++;
+
+    for (var start = 0; start < json.length;) {
+      var end = Math.min(start + CHUNK_SIZE, json.length);
+      var last = json.charCodeAt(end - 1);
+
+      // Keep surrogate pairs together
+      if (end < json.length && last >= 0xD800 && last <= 0xDBFF) {
+        end--;
+      }
+
+      window.external.invoke(JSON.stringify({ __webview: 'chunk', id: id, data: json.slice(start, end), last: end === json.length }));
+      start = end;
+    }
+  }
+
+  function describe(e) {
+    return {
+      message: String(e && e.message !== undefined ? e.message : e),
+      stack: (e && e.stack) || null,
+    };
+  }
+
+  function toError(payload) {
+    var error = new Error(payload.message);
+    error.name = payload.name;
+    return error;
+  }
+
+  function settle(id) {
+    var call = pending[id];
+    delete pending[id];
+
+    if (call && call.cleanup) {
+      call.cleanup();
+    }
+
+    return call;
+  }
+
+  function abortError(signal) {
+    if (signal && signal.reason !== undefined) {
+      return signal.reason;
+    }
+
+    var error = new Error('The call was aborted');
+    error.name = 'AbortError';
+    return error;
+  }
+
+  // Rejects the call `id` and lets Rust know it is no longer wanted.
+  function cancel(id, signal) {
+    var call = settle(id);
+
+    if (call) {
+      post({ __webview: 'cancel', id: Number(id) });
+      call.reject(abortError(signal));
+    }
+  }
+
+  var rust = {
+    // Calls into Rust; the returned promise settles with the handler's result. Aborting
+    // `options.signal` rejects it and cancels the call in Rust.
+    call: function(name, args, options) {
+      var id = nextId++;
+      var signal = options && options.signal;
+
+      return new Promise(function(resolve, reject) {
+        if (signal && signal.aborted) {
+          reject(abortError(signal));
+          return;
+        }
+
+        pending[id] = { resolve: resolve, reject: reject };
+
+        if (signal) {
+          var abort = function() {
+            cancel(id, signal);
+          };
+
+          signal.addEventListener('abort', abort);
+          pending[id].cleanup = function() {
+            signal.removeEventListener('abort', abort);
+          };
+        }
+
+        var json = JSON.stringify({ __webview: 'call', id: id, name: name, args: args === undefined ? null : args }, replacer);
+        var size = LIMIT === null ? 0 : new TextEncoder().encode(json).length;
+
+        if (LIMIT !== null && size > LIMIT) {
+          settle(id);
+          reject(toError({
+            name: 'MessageTooLarge',
+            message: 'Message of ' + size + ' bytes exceeds the limit of ' + LIMIT + ' bytes.',
+          }));
+          return;
+        }
+
+        send(json);
+      });
+    },
+
+    // Adds a listener for events emitted from Rust.
+    on: function(event, listener) {
+      (listeners[event] = listeners[event] || []).push(listener);
+    },
+
+    // Receives the next channel named `name` opened in Rust.
+    channel: function(name) {
+      var channel = (unclaimed[name] || []).shift();
+
+      if (!channel) {
+        channel = new Channel();
+        (claims[name] = claims[name] || []).push(channel);
+      }
+
+      return channel;
+    },
+
+    // Removes a listener added with `on`, or every listener of `event` if none is given.
+    off: function(event, listener) {
+      if (listener === undefined) {
+        delete listeners[event];
+        return;
+      }
+
+      listeners[event] = (listeners[event] || []).filter(function(l) {
+        return l !== listener;
+      });
+    },
+  };
+
+  // A channel opened in Rust with `Handle::channel`, consumed as an async iterator.
+  function Channel() {
+    this._items = [];
+    this._readers = [];
+    this._done = false;
+    this._error = null;
+  }
+
+  Channel.prototype.next = function() {
+    var channel = this;
+
+    if (channel._items.length) {
+      return Promise.resolve({ value: channel._items.shift(), done: false });
+    }
+
+    if (channel._error) {
+      var error = channel._error;
+      channel._error = null;
+      return Promise.reject(error);
+    }
+
+    if (channel._done) {
+      return Promise.resolve({ value: undefined, done: true });
+    }
+
+    return new Promise(function(resolve, reject) {
+      channel._readers.push({ resolve: resolve, reject: reject });
+    });
+  };
+
+  // Stops consuming the channel; further items are discarded.
+  Channel.prototype.return = function() {
+    this._items = [];
+    this._end(null);
+    return Promise.resolve({ value: undefined, done: true });
+  };
+
+  // Returns the channel as a ReadableStream.
+  Channel.prototype.stream = function() {
+    var channel = this;
+
+    return new ReadableStream({
+      pull: function(controller) {
+        return channel.next().then(function(result) {
+          if (result.done) {
+            controller.close();
+          } else {
+            controller.enqueue(result.value);
+          }
+        });
+      },
+      cancel: function() {
+        channel.return();
+      },
+    });
+  };
+
+  Channel.prototype._push = function(value) {
+    if (this._done) {
+      return;
+    }
+
+    if (this._readers.length) {
+      this._readers.shift().resolve({ value: value, done: false });
+    } else {
+      this._items.push(value);
+    }
+  };
+
+  Channel.prototype._end = function(error) {
+    if (this._done) {
+      return;
+    }
+
+    this._done = true;
+
+    var readers = this._readers;
+    this._readers = [];
+
+    if (error && readers.length) {
+      readers.shift().reject(error);
+      error = null;
+    }
+
+    this._error = error;
+
+    readers.forEach(function(reader) {
+      reader.resolve({ value: undefined, done: true });
+    });
+  };
+
+  if (typeof Symbol !== 'undefined' && Symbol.asyncIterator) {
+    Channel.prototype[Symbol.asyncIterator] = function() {
+      return this;
+    };
+  }
+
+  // Returns the channel state for a message from Rust, pairing channels with `rust.channel`
+  // calls by name in the order they were opened.
+  function channelFor(message) {
+    var channel = channels[message.id];
+
+    if (!channel) {
+      channel = (claims[message.name] || []).shift();
+
+      if (!channel) {
+        channel = new Channel();
+        (unclaimed[message.name] = unclaimed[message.name] || []).push(channel);
+      }
+
+      channel._seq = 0;
+      channels[message.id] = channel;
+    }
+
+    return channel;
+  }
+
+  // Handles channel messages, which arrive in the order they were sent. Items dropped by a full
+  // dispatch queue leave gaps in their sequence numbers, which are skipped.
+  function receiveChannel(message) {
+    var channel = channelFor(message);
+
+    if (message.seq < channel._seq) {
+      return;
+    }
+
+    channel._seq = message.seq + 1;
+
+    if (message.type === 'channelItem') {
+      channel._push(message.value);
+    } else {
+      delete channels[message.id];
+      channel._end(message.error ? toError(message.error) : null);
+    }
+  }
+
+  function emit(event, payload) {
+    (listeners[event] || []).slice().forEach(function(listener) {
+      try {
+        listener(payload);
+      } catch (e) {
+        // Report without preventing the remaining listeners from running.
+        setTimeout(function() {
+          throw e;
+        });
+      }
+    });
+  }
+
+  // Evaluates an expression for `WebView::eval_with_result`, reporting its (settled) value.
+  Object.defineProperty(rust, '_evaluate', {
+    value: function(id, expression) {
+      function evaluated(value, error) {
+        try {
+          post({ __webview: 'evaluated', id: id, value: value === undefined ? null : value, error: error });
+        } catch (e) {
+          // The value could not be serialized
+          post({ __webview: 'evaluated', id: id, value: null, error: describe(e) });
+        }
+      }
+
+      new Promise(function(resolve) {
+        resolve(expression());
+      }).then(function(value) {
+        evaluated(value);
+      }, function(e) {
+        evaluated(null, describe(e));
+      });
+    },
+  });
+
+  // Entry point for messages sent from Rust.
+  Object.defineProperty(rust, '_receive', {
+    value: function(message) {
+      var call;
+
+      message = revive(message);
+
+      switch (message.type) {
+        case 'resolve':
+          call = settle(message.id);
+          if (call) {
+            call.resolve(message.value);
+          }
+          break;
+        case 'reject':
+          call = settle(message.id);
+          if (call) {
+            call.reject(toError(message.error));
+          }
+          break;
+        case 'channelItem':
+        case 'channelEnd':
+          receiveChannel(message);
+          break;
+        case 'emit':
+          emit(message.event, message.payload);
+          break;
+        case 'error':
+          emit('error', toError(message.error));
+          break;
+      }
+    },
+  });
+
+  // Entry point for messages too large to be sent from Rust at once.
+  Object.defineProperty(rust, '_chunk', {
+    value: function(id, data, last) {
+      chunks[id] = (chunks[id] || '') + data;
+
+      if (last) {
+        var json = chunks[id];
+        delete chunks[id];
+        rust._receive(JSON.parse(json));
+      }
+    },
+  });
+
+  // Rust only passes this on once per page
+  document.addEventListener('DOMContentLoaded', function() {
+    post({ __webview: 'domContentLoaded' });
+  });
+
+  // Calls still pending when the page goes away will never be answered
+  window.addEventListener('pagehide', function() {
+    Object.keys(pending).forEach(function(id) {
+      cancel(id);
+    });
+  });
+
+  Object.defineProperty(window, 'rust', { value: rust });
+})();
@@ -0,0 +1,245 @@
+use crate::error::{
+    Error,
+    WVResult,
+};
+use crate::escape::to_js;
+use crate::WebView;
+use serde_derive::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::Value;
+
+/// JavaScript providing the `window.rust` API, installed at the start of every document.
+const SCRIPT: &str = include_str!("bridge.js");
+
+/// Returns the bridge script, which prefixes the messages it sends with `key`, and rejects calls
+/// whose message is larger than `limit` in bytes before sending them.
+pub fn source(key: &str, limit: Option<usize>) -> String {
+    let limit = limit.map(|limit| limit.to_string()).unwrap_or_else(|| "null".to_string());
+
+    SCRIPT.replace("__WEBVIEW_KEY__", key).replace("__WEBVIEW_LIMIT__", &limit)
+}
+
+/// Prefix of every message sent by the bridge script.
+const TAG: &str = r#"{"__webview":"#;
+
+/// Size in bytes above which messages to the bridge script are split into chunks.
+const CHUNK_SIZE: usize = 512 * 1024;
+
+/// A message sent by the bridge script through `external.invoke`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "__webview", rename_all = "camelCase")]
+pub enum Incoming {
+    Call {
+        id: u64,
+        name: String,
+        #[serde(default)]
+        args: Value,
+    },
+    Evaluated {
+        id: u64,
+        #[serde(default)]
+        value: Value,
+        error: Option<JsError>,
+    },
+    /// A call abandoned by JavaScript.
+    Cancel {
+        id: u64,
+    },
+    /// Part of a message too large to be sent at once.
+    Chunk {
+        id: u64,
+        data: String,
+        last: bool,
+    },
+    /// The document has been parsed.
+    DomContentLoaded,
+}
+
+impl Incoming {
+    /// Parses `message`, returning `None` if it was not sent by the bridge script.
+    pub fn parse(message: &str) -> Option<WVResult<Incoming>> {
+        if message.starts_with(TAG) {
+            Some(serde_json::from_str(message).map_err(Error::from))
+        } else {
+            None
+        }
+    }
+}
+
+/// An exception thrown in JavaScript.
+#[derive(Debug, Deserialize)]
+pub struct JsError {
+    message: String,
+    stack: Option<String>,
+}
+
+impl From<JsError> for Error {
+    fn from(e: JsError) -> Error {
+        Error::JsException {
+            message: e.message,
+            stack: e.stack,
+        }
+    }
+}
+
+/// A message delivered to the bridge script through `eval`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Outgoing<'a> {
+    Resolve { id: u64, value: &'a Value },
+    Reject { id: u64, error: ErrorPayload },
+    Emit { event: &'a str, payload: &'a Value },
+    /// A handler error forwarded to JavaScript, emitted there as an `error` event.
+    Error { error: ErrorPayload },
+    ChannelItem { id: usize, name: &'a str, seq: u64, value: &'a Value },
+    ChannelEnd { id: usize, name: &'a str, seq: u64, error: Option<ErrorPayload> },
+}
+
+/// An [`Error`] as seen by JavaScript.
+#[derive(Debug, Serialize)]
+pub struct ErrorPayload {
+    name: &'static str,
+    message: String,
+}
+
+impl<'a> From<&'a Error> for ErrorPayload {
+    fn from(e: &'a Error) -> ErrorPayload {
+        ErrorPayload {
+            name: e.name(),
+            message: e.to_string(),
+        }
+    }
+}
+
+/// Delivers `message` to the bridge script running in `webview`.
+///
+/// Messages larger than `CHUNK_SIZE` are passed as a JSON string in several evaluations, and only
+/// parsed once complete.
+pub fn send<T>(webview: &mut WebView<T>, message: &Outgoing) -> WVResult {
+    if !webview.state_data().bridge {
+        return Err(Error::Unsupported("the JavaScript bridge"));
+    }
+
+    let json = to_js(message)?;
+
+    if json.len() <= CHUNK_SIZE {
+        return webview.eval(&receive(&json));
+    }
+
+    let data = webview.state_data_mut();
+    let id = data.next_chunk_id;
+
+    data.next_chunk_id += 1;
+
+    let mut rest = json.as_str();
+
+    while !rest.is_empty() {
+        let mut end = CHUNK_SIZE.min(rest.len());
+
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        let (chunk, remainder) = rest.split_at(end);
+
+        webview.eval(&format!("window.rust._chunk({}, {}, {})", id, to_js(chunk)?, remainder.is_empty()))?;
+        rest = remainder;
+    }
+
+    Ok(())
+}
+
+/// Returns a script delivering `message` to the bridge script in a single evaluation.
+pub fn script(message: &Outgoing) -> WVResult<String> {
+    Ok(receive(&to_js(message)?))
+}
+
+fn receive(json: &str) -> String {
+    format!("window.rust._receive({})", json)
+}
+
+/// A message being received in chunks.
+#[derive(Debug)]
+pub struct Partial {
+    id: u64,
+    /// The chunks received so far, or `None` once the message has been rejected, until its last
+    /// chunk arrives.
+    data: Option<String>,
+}
+
+/// Reassembles a message sent in chunks, returning it once `last` has been received.
+///
+/// The bridge script sends the chunks of a message one after the other, so only one message is
+/// held in `partial`: chunks of another message drop what is left of the previous one.
+///
+/// # Errors
+///
+/// Returns [`Error::MessageTooLarge`], and drops the chunks received so far, once the message
+/// exceeds `limit` bytes, if any. Its remaining chunks are then ignored.
+///
+/// [`Error::MessageTooLarge`]: enum.Error.html#variant.MessageTooLarge
+pub fn reassemble(partial: &mut Option<Partial>, id: u64, data: &str, last: bool, limit: Option<usize>) -> WVResult<Option<String>> {
+    let message = match partial {
+        Some(message) if message.id == id => message,
+        _ => partial.insert(Partial { id, data: Some(String::new()) }),
+    };
+    let result = match message.data.as_mut() {
+        Some(received) => match limit {
+            Some(limit) if received.len() + data.len() > limit => {
+                let size = received.len() + data.len();
+                message.data = None;
+                Err(Error::MessageTooLarge { size, limit })
+            },
+            _ => {
+                received.push_str(data);
+                Ok(None)
+            },
+        },
+        None => Ok(None),
+    };
+
+    match (result, last) {
+        (Ok(None), true) => Ok(partial.take().and_then(|message| message.data)),
+        (result, true) => {
+            *partial = None;
+            result
+        },
+        (result, false) => result,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reassemble() {
+        let mut partial = None;
+
+        assert_eq!(None, reassemble(&mut partial, 1, r#"{"__webview":"#, false, Some(64)).unwrap());
+        assert_eq!(Some(r#"{"__webview":"call"}"#.to_string()), reassemble(&mut partial, 1, r#""call"}"#, true, Some(64)).unwrap());
+        assert!(partial.is_none());
+
+        // Chunks of another message drop those left over
+        assert_eq!(None, reassemble(&mut partial, 2, "{", false, Some(64)).unwrap());
+        assert_eq!(None, reassemble(&mut partial, 3, "[", false, Some(64)).unwrap());
+        assert_eq!(Some("[]".to_string()), reassemble(&mut partial, 3, "]", true, Some(64)).unwrap());
+
+        match reassemble(&mut partial, 4, &"a".repeat(65), false, Some(64)) {
+            Err(Error::MessageTooLarge { size: 65, limit: 64 }) => (),
+            r => panic!("Unexpected result {:?}", r),
+        }
+
+        // The rest of a rejected message is ignored, rather than taken for a message of its own
+        assert_eq!(None, reassemble(&mut partial, 4, "a", false, Some(64)).unwrap());
+        assert_eq!(None, reassemble(&mut partial, 4, "}", true, Some(64)).unwrap());
+        assert!(partial.is_none());
+
+        match Incoming::parse(r#"{"__webview":"chunk","id":3,"data":"{\"a\":1}","last":true}"#) {
+            Some(Ok(Incoming::Chunk { id: 3, ref data, last: true })) if data == r#"{"a":1}"# => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use crate::typescript::TypeScript;
+use serde::de::{
+    self,
+    Deserialize,
+    Deserializer,
+    MapAccess,
+    SeqAccess,
+    Visitor,
+};
+use serde::ser::{
+    Serialize,
+    SerializeMap,
+    Serializer,
+};
+use std::fmt;
+use std::ops::{
+    Deref,
+    DerefMut,
+};
+
+/// Key of the object binary data is encoded as, e.g. `{"__webviewBytes":"AAEC"}`, reserved to
+/// this library like the `__webview` tag of bridge messages.
+const KEY: &str = "__webviewBytes";
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Binary data passed between Rust and JavaScript.
+///
+/// Received as a `Uint8Array` in JavaScript, and deserialized from an `ArrayBuffer` or any typed
+/// array passed to `rust.call`. On the way it is encoded as base64, so it may contain any byte,
+/// including zero.
+///
+/// # Example
+///
+/// ```no_run
+/// extern crate webview;
+///
+/// use std::fs;
+/// use webview::*;
+///
+/// fn main() {
+///     WebViewBuilder::new()
+///         .content(Content::Html(r#"<script>
+///             rust.call('read', 'logo.png').then(function(bytes) {
+///                 var image = new Image();
+///                 image.src = URL.createObjectURL(new Blob([bytes]));
+///                 document.body.appendChild(image);
+///             });
+///         </script>"#))
+///         .user_data(())
+///         .command("read", |_webview, path: String| {
+///             fs::read(path).map(Bytes::from).map_err(Error::custom)
+///         })
+///         .build()
+///         .unwrap()
+///         .run()
+///         .unwrap();
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Bytes(pub Vec<u8>);
+
+impl Bytes {
+    /// Returns the underlying buffer.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl Deref for Bytes {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        &self.0
+    }
+}
+
+impl DerefMut for Bytes {
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.0
+    }
+}
+
+impl From<Vec<u8>> for Bytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Bytes(bytes)
+    }
+}
+
+impl<'a> From<&'a [u8]> for Bytes {
+    fn from(bytes: &'a [u8]) -> Self {
+        Bytes(bytes.to_vec())
+    }
+}
+
+impl From<Bytes> for Vec<u8> {
+    fn from(bytes: Bytes) -> Self {
+        bytes.0
+    }
+}
+
+impl Serialize for Bytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(KEY, &encode(&self.0))?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(BytesVisitor)
+    }
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Bytes;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "binary data")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Bytes, A::Error> {
+        match map.next_key::<String>()? {
+            Some(ref key) if key == KEY => {
+                let encoded: String = map.next_value()?;
+
+                decode(&encoded)
+                    .map(Bytes)
+                    .ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(&encoded), &"base64"))
+            },
+            _ => Err(de::Error::missing_field(KEY)),
+        }
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Bytes, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+
+        Ok(Bytes(bytes))
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Bytes, E> {
+        Ok(Bytes(bytes.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Bytes, E> {
+        Ok(Bytes(bytes))
+    }
+}
+
+impl TypeScript for Bytes {
+    fn ts_type() -> String {
+        "Uint8Array".to_string()
+    }
+}
+
+/// Encodes `bytes` as padded base64.
+fn encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len() / 3 * 4 + 4);
+
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &byte)| n | u32::from(byte) << (16 - 8 * i));
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Decodes padded base64, returning `None` if `encoded` is not valid.
+fn decode(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.as_bytes();
+
+    if encoded.chunks(4).any(|chunk| chunk.len() < 4) {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(encoded.len() / 4 * 3);
+
+    for (index, chunk) in encoded.chunks(4).enumerate() {
+        let last = index == encoded.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+
+        if padding > 2 || (padding > 0 && !last) {
+            return None;
+        }
+
+        let mut n = 0u32;
+
+        for &c in &chunk[..4 - padding] {
+            let value = ALPHABET.iter().position(|&a| a == c)?;
+            n = n << 6 | value as u32;
+        }
+
+        n <<= 6 * padding as u32;
+
+        bytes.extend_from_slice(&[(n >> 16) as u8, (n >> 8) as u8, n as u8][..3 - padding]);
+    }
+
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        let cases: &[(&[u8], &str)] = &[
+            (b"", ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+            (b"\0\xFF\x10", "AP8Q"),
+        ];
+
+        for &(bytes, encoded) in cases {
+            assert_eq!(encoded, encode(bytes));
+            assert_eq!(Some(bytes.to_vec()), decode(encoded));
+        }
+
+        assert_eq!(None, decode("Zm9"));
+        assert_eq!(None, decode("Zg==Zm9v"));
+        assert_eq!(None, decode("Zm9*"));
+    }
+
+    #[test]
+    fn test_serde() {
+        let bytes = Bytes(vec![0, 1, 2, 255]);
+        let json = serde_json::to_string(&bytes).unwrap();
+
+        assert_eq!(r#"{"__webviewBytes":"AAEC/w=="}"#, json);
+        assert_eq!(bytes, serde_json::from_str(&json).unwrap());
+        assert_eq!(bytes, serde_json::from_str("[0, 1, 2, 255]").unwrap());
+        assert!(serde_json::from_str::<Bytes>(r#"{"__webviewBytes":"!"}"#).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::bridge::{
+    self,
+    ErrorPayload,
+    Outgoing,
+};
+use crate::error::{
+    Error,
+    WVResult,
+};
+use crate::handle::Handle;
+use serde::Serialize;
+use std::marker::PhantomData;
+use std::sync::atomic::{
+    AtomicUsize,
+    Ordering,
+};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// The sending half of a channel streaming items to JavaScript, created with
+/// [`Handle::channel()`].
+///
+/// Items are received in the order they were sent. The channel is closed when the sender is
+/// dropped, unless it was ended with [`close()`] or [`error()`] before.
+///
+/// [`Handle::channel()`]: struct.Handle.html#method.channel
+/// [`close()`]: struct.Sender.html#method.close
+/// [`error()`]: struct.Sender.html#method.error
+pub struct Sender<T, P> {
+    handle: Handle<T>,
+    id: usize,
+    name: String,
+    seq: u64,
+    ended: bool,
+    _payload: PhantomData<fn(P)>,
+}
+
+impl<T, P: Serialize> Sender<T, P> {
+    pub(crate) fn new(handle: Handle<T>, name: &str) -> Self {
+        Sender {
+            handle,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            name: name.to_string(),
+            seq: 0,
+            ended: false,
+            _payload: PhantomData,
+        }
+    }
+
+    /// Returns the name the channel is received by in JavaScript.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Sends `item` to JavaScript. It is serialized on the calling thread.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Json`] if `item` cannot be serialized, or [`Error::Dispatch`] if the
+    /// [`WebView`] has been dropped.
+    ///
+    /// [`WebView`]: struct.WebView.html
+    /// [`Error::Json`]: enum.Error.html#variant.Json
+    /// [`Error::Dispatch`]: enum.Error.html#variant.Dispatch
+    pub fn send(&mut self, item: P) -> WVResult {
+        let value = serde_json::to_value(item)?;
+        let (id, seq, name) = (self.id, self.next_seq(), self.name.clone());
+
+        self.handle.dispatch(move |webview| {
+            bridge::send(webview, &Outgoing::ChannelItem { id, name: &name, seq, value: &value })
+        })
+    }
+
+    /// Ends the stream successfully.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Dispatch`] if the [`WebView`] has been dropped.
+    ///
+    /// [`WebView`]: struct.WebView.html
+    /// [`Error::Dispatch`]: enum.Error.html#variant.Dispatch
+    pub fn close(mut self) -> WVResult {
+        self.end(None)
+    }
+
+    /// Ends the stream with `error`, which the JavaScript consumer receives as an `Error` named
+    /// after its variant.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Dispatch`] if the [`WebView`] has been dropped.
+    ///
+    /// [`WebView`]: struct.WebView.html
+    /// [`Error::Dispatch`]: enum.Error.html#variant.Dispatch
+    pub fn error<E: Into<Error>>(mut self, error: E) -> WVResult {
+        self.end(Some(ErrorPayload::from(&error.into())))
+    }
+}
+
+impl<T, P> Sender<T, P> {
+    fn next_seq(&mut self) -> u64 {
+        let seq = self.seq;
+
+        self.seq += 1;
+        seq
+    }
+
+    fn end(&mut self, error: Option<ErrorPayload>) -> WVResult {
+        let (id, seq, name) = (self.id, self.next_seq(), self.name.clone());
+
+        self.ended = true;
+        self.handle.dispatch(move |webview| {
+            bridge::send(webview, &Outgoing::ChannelEnd { id, name: &name, seq, error })
+        })
+    }
+}
+
+impl<T, P> Drop for Sender<T, P> {
+    fn drop(&mut self) {
+        if !self.ended {
+            // The WebView may already be gone, in which case there is no one left to notify
+            let _ = self.end(None);
+        }
+    }
+}
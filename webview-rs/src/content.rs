@@ -1,12 +1,189 @@
+use crate::assets::{
+    self,
+    Assets,
+};
+use crate::error::WVResult;
+use crate::mime;
+use crate::protocol::{
+    ProtocolHandler,
+    Response,
+};
+use std::fs;
+use std::path::{
+    Component,
+    Path,
+};
+use urlencoding::encode;
+
 /// Content displayable inside a [`WebView`].
 ///
 /// # Variants
 ///
 /// - `Url` - Content to be fetched from a URL.
 /// - `Html` - A string containing literal HTML.
+/// - `File` - The path to an HTML file, from which relative URLs are resolved.
+/// - `Dir` - The path to a directory holding a frontend, whose `index.html` is loaded.
+/// - `Assets` - Files embedded in the binary with the [`assets!`] macro, best created with
+///   [`Content::assets`].
+///
+/// `File` is loaded through a `file://` URL, so the page can load the scripts, stylesheets and
+/// images next to it, with MIME types as determined by the platform.
+///
+/// `Dir` is served like `Assets`, from `app://localhost/`, but read from disk as requested. Root
+/// relative URLs, `fetch` and module scripts then work as they would on a web server, and files
+/// are served with the MIME type of their extension. Like custom URI schemes, this is only
+/// supported by WebKitGTK.
+///
+/// # Example
+///
+/// ```no_run
+/// # use webview::*;
+/// #
+/// # fn run() -> WVResult {
+/// // A single page application built into `frontend/dist`
+/// WebViewBuilder::new()
+///     .content(Content::Dir("frontend/dist"))
+///     .user_data(())
+///     .invoke_handler(|_webview, _arg| Ok(()))
+///     .build()?
+///     .run()
+/// # }
+/// ```
 ///
 /// [`WebView`]: struct.WebView.html
+/// [`assets!`]: macro.assets.html
+/// [`Content::assets`]: enum.Content.html#method.assets
 pub enum Content<T: AsRef<str>> {
     Url(T),
     Html(T),
+    File(T),
+    Dir(T),
+    Assets(Assets),
+}
+
+impl<T: AsRef<str>> Content<T> {
+    /// Returns the URL the content is loaded from.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if the path of a `File` or `Dir` cannot be resolved.
+    ///
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    pub fn to_url(&self) -> WVResult<String> {
+        match self {
+            Content::Url(url) => Ok(url.as_ref().to_string()),
+            Content::Html(html) => Ok(format!("data:text/html,{}", encode(html.as_ref()))),
+            Content::File(path) => file_url(Path::new(path.as_ref())),
+            Content::Dir(path) => {
+                Path::new(path.as_ref()).canonicalize()?;
+                Ok(format!("{}://localhost/", assets::SCHEME))
+            },
+            Content::Assets(assets) => Ok(assets.url()),
+        }
+    }
+
+    /// Returns the custom URI scheme `Dir` and `Assets` are served through, with the handler
+    /// serving them.
+    pub(crate) fn protocol(&self) -> WVResult<Option<(&'static str, ProtocolHandler<'static>)>> {
+        match self {
+            Content::Dir(path) => {
+                let root = Path::new(path.as_ref()).canonicalize()?;
+                Ok(Some((assets::SCHEME, Box::new(move |request| serve_file(&root, &request.path())))))
+            },
+            Content::Assets(assets) => {
+                let assets = *assets;
+                Ok(Some((assets.scheme, Box::new(move |request| assets.respond(request)))))
+            },
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Content<&'static str> {
+    /// Creates `Content::Assets`, whose string type could not be inferred otherwise.
+    pub fn assets(assets: Assets) -> Self {
+        Content::Assets(assets)
+    }
+}
+
+/// Returns the `file://` URL of `path`, which must exist.
+fn file_url(path: &Path) -> WVResult<String> {
+    let path = path.canonicalize()?;
+    let path = path.to_string_lossy().replace('\\', "/");
+    // Strip the verbatim prefix of canonical paths on Windows, e.g. `\\?\C:\`
+    let path = path.trim_start_matches("//?/");
+    let mut url = String::from("file://");
+
+    if !path.starts_with('/') {
+        url.push('/');
+    }
+
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b':' | b'-' | b'.' | b'_' | b'~' => {
+                url.push(byte as char)
+            },
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    Ok(url)
+}
+
+/// Serves the file at the decoded URL `path` below `root`, or the `index.html` of a directory.
+pub(crate) fn serve_file(root: &Path, path: &str) -> Response {
+    let mut file = root.to_path_buf();
+
+    for component in Path::new(path.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(part) => file.push(part),
+            Component::CurDir => (),
+            // Never serve anything outside of the root
+            _ => return Response::not_found(),
+        }
+    }
+
+    if file.is_dir() {
+        file.push("index.html");
+    }
+
+    match fs::read(&file) {
+        Ok(data) => Response::new(data).mime(mime::guess(&file.to_string_lossy())),
+        Err(_) => Response::not_found(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_to_url() {
+        let dir = env::temp_dir().join("webview content test");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("index.html"), "<html></html>").unwrap();
+
+        let url = Content::File(dir.join("index.html").to_str().unwrap()).to_url().unwrap();
+
+        assert!(url.starts_with("file:///"));
+        assert!(url.ends_with("/webview%20content%20test/index.html"));
+        assert_eq!("app://localhost/", Content::Dir(dir.to_str().unwrap()).to_url().unwrap());
+        assert_eq!("data:text/html,%3Cp%3E", Content::Html("<p>").to_url().unwrap());
+        assert!(Content::File(dir.join("missing.html").to_str().unwrap()).to_url().is_err());
+        assert!(Content::Dir(dir.join("missing").to_str().unwrap()).to_url().is_err());
+    }
+
+    #[test]
+    fn test_serve_file() {
+        let root = env::temp_dir().join("webview serve_file test");
+        fs::create_dir_all(root.join("js")).unwrap();
+        fs::write(root.join("index.html"), "<html>").unwrap();
+        fs::write(root.join("js/app.js"), "app()").unwrap();
+
+        assert_eq!(&b"<html>"[..], &*serve_file(&root, "/").body);
+        assert_eq!(Some("text/javascript"), serve_file(&root, "/js/app.js").mime.as_deref());
+        assert_eq!(404, serve_file(&root, "/js/../../secret").status);
+        assert_eq!(404, serve_file(&root, "/missing.js").status);
+    }
 }
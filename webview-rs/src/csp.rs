@@ -0,0 +1,45 @@
+/// Inserts a `<meta>` element applying the Content Security Policy `policy` at the start of
+/// `html`, after its doctype if any, so that it applies before anything else is loaded.
+pub fn insert_meta(html: &str, policy: &str) -> String {
+    let start = html.len() - html.trim_start().len();
+    let rest = &html[start..];
+    let at = match rest.get(..9) {
+        Some(doctype) if doctype.eq_ignore_ascii_case("<!doctype") => rest.find('>').map(|i| start + i + 1),
+        _ => None,
+    }
+    .unwrap_or(0);
+    let policy: String = policy
+        .chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '"' => "&quot;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            c => c.to_string(),
+        })
+        .collect();
+
+    format!(
+        "{}<meta http-equiv=\"Content-Security-Policy\" content=\"{}\">{}",
+        &html[..at],
+        policy,
+        &html[at..]
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_insert_meta() {
+        assert_eq!(
+            "<meta http-equiv=\"Content-Security-Policy\" content=\"default-src 'none'\"><p>hi</p>",
+            insert_meta("<p>hi</p>", "default-src 'none'")
+        );
+        assert_eq!(
+            "\n<!DOCTYPE html><meta http-equiv=\"Content-Security-Policy\" content=\"a &quot;b&gt;\"><html>",
+            insert_meta("\n<!DOCTYPE html><html>", "a \"b>")
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use crate::error::{
+    Error,
+    WVResult,
+};
+use crate::handle::Handle;
+use crate::rpc;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+use std::sync::Arc;
+
+/// Tells whether a call from JavaScript has been cancelled, either with an `AbortSignal` passed to
+/// `rust.call(name, args, { signal })` or by the page going away.
+///
+/// Clones share the same state, so a token can be handed to worker threads which poll it.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Creates a token which has not been cancelled.
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    /// Returns `true` once the call has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Cancels the call.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns `true` if `other` is a clone of this token.
+    pub(crate) fn same(&self, other: &CancellationToken) -> bool {
+        Arc::ptr_eq(&self.cancelled, &other.cancelled)
+    }
+}
+
+/// The pending response to a call from JavaScript, settled from any thread.
+///
+/// Passed to commands registered with [`WebViewBuilder::deferred_command`]. Once the call has been
+/// cancelled, settling it does nothing. Dropping it without settling the call rejects it.
+///
+/// [`WebViewBuilder::deferred_command`]: struct.WebViewBuilder.html#method.deferred_command
+pub struct Deferred<T> {
+    handle: Handle<T>,
+    id: u64,
+    /// The page which made the call, as call ids are only unique within a page.
+    page: u64,
+    token: CancellationToken,
+    settled: bool,
+}
+
+impl<T> Deferred<T> {
+    pub(crate) fn new(handle: Handle<T>, id: u64, page: u64, token: CancellationToken) -> Self {
+        Deferred {
+            handle,
+            id,
+            page,
+            token,
+            settled: false,
+        }
+    }
+
+    /// Returns the id of the call.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the token telling whether the call has been cancelled.
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// Returns `true` once the call has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Resolves the promise returned to JavaScript with `value`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Json`] if `value` cannot be serialized, or [`Error::Dispatch`] if the
+    /// [`WebView`] has been dropped.
+    ///
+    /// [`WebView`]: struct.WebView.html
+    /// [`Error::Json`]: enum.Error.html#variant.Json
+    /// [`Error::Dispatch`]: enum.Error.html#variant.Dispatch
+    pub fn resolve<R: Serialize>(mut self, value: R) -> WVResult {
+        let result = serde_json::to_value(value).map_err(Error::from);
+
+        self.settle(result)
+    }
+
+    /// Rejects the promise returned to JavaScript with `error`, which like errors returned by
+    /// commands is then returned from [`step()`] unless errors are forwarded to JavaScript.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Dispatch`] if the [`WebView`] has been dropped.
+    ///
+    /// [`WebView`]: struct.WebView.html
+    /// [`Error::Dispatch`]: enum.Error.html#variant.Dispatch
+    /// [`step()`]: struct.WebView.html#method.step
+    pub fn reject<E: Into<Error>>(mut self, error: E) -> WVResult {
+        self.settle(Err(error.into()))
+    }
+
+    fn settle(&mut self, result: WVResult<Value>) -> WVResult {
+        self.settled = true;
+
+        if self.token.is_cancelled() {
+            return Ok(());
+        }
+
+        let (id, page, token) = (self.id, self.page, self.token.clone());
+
+        self.handle.dispatch(move |webview| {
+            let data = webview.state_data_mut();
+
+            // The call may have been cancelled while this was queued, or made by a previous page,
+            // in which case the id may now belong to a call of the current page
+            if token.is_cancelled() || data.page != page {
+                return Ok(());
+            }
+
+            if matches!(data.cancellations.get(&id), Some(t) if t.same(&token)) {
+                data.cancellations.remove(&id);
+            }
+
+            rpc::respond(webview, id, result)
+        })
+    }
+}
+
+impl<T> Drop for Deferred<T> {
+    fn drop(&mut self) {
+        if !self.settled {
+            let _ = self.settle(Err(Error::custom("call dropped without a response")));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_same_token() {
+        let token = CancellationToken::new();
+
+        assert!(token.same(&token.clone()));
+        assert!(!token.same(&CancellationToken::new()));
+
+        token.clone().cancel();
+        assert!(token.is_cancelled());
+    }
+}
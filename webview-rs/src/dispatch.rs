@@ -0,0 +1,255 @@
+use crate::error::{
+    Error,
+    WVResult,
+};
+use crate::WebView;
+use std::collections::VecDeque;
+use std::sync::{
+    Condvar,
+    Mutex,
+};
+use std::thread::{
+    self,
+    ThreadId,
+};
+
+pub type Dispatched<T> = Box<dyn FnOnce(&mut WebView<T>) -> WVResult + Send>;
+
+/// What [`Handle::dispatch`] does when the dispatch queue is full. See
+/// [`WebViewBuilder::dispatch_queue`].
+///
+/// [`Handle::dispatch`]: struct.Handle.html#method.dispatch
+/// [`WebViewBuilder::dispatch_queue`]: struct.WebViewBuilder.html#method.dispatch_queue
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    /// Wait until there is room. Dispatching from the WebView thread itself never waits, as the
+    /// queue could not drain in the meantime.
+    Block,
+    /// Drop the oldest queued closure to make room. The closure evaluating the scripts batched
+    /// with [`Handle::eval_batched`] is kept, as they would otherwise wait for the next one.
+    ///
+    /// [`Handle::eval_batched`]: struct.Handle.html#method.eval_batched
+    DropOldest,
+    /// Return [`Error::QueueFull`].
+    ///
+    /// [`Error::QueueFull`]: enum.Error.html#variant.QueueFull
+    Error,
+}
+
+struct State<T> {
+    /// Queued closures, with whether [`Overflow::DropOldest`] may drop them.
+    ///
+    /// [`Overflow::DropOldest`]: enum.Overflow.html#variant.DropOldest
+    closures: VecDeque<(Dispatched<T>, bool)>,
+    /// Whether the queue is being drained, or a closure draining it has been dispatched.
+    scheduled: bool,
+    /// Set once the WebView has been dropped.
+    closed: bool,
+}
+
+/// Closures dispatched through a [`Handle`], run in order on the WebView thread.
+///
+/// [`Handle`]: struct.Handle.html
+pub struct DispatchQueue<T> {
+    state: Mutex<State<T>>,
+    room: Condvar,
+    capacity: Option<(usize, Overflow)>,
+    owner: ThreadId,
+}
+
+impl<T> DispatchQueue<T> {
+    /// Creates a queue owned by the current thread, unbounded unless `capacity` is given.
+    pub fn new(capacity: Option<(usize, Overflow)>) -> Self {
+        DispatchQueue {
+            state: Mutex::new(State {
+                closures: VecDeque::new(),
+                scheduled: false,
+                closed: false,
+            }),
+            room: Condvar::new(),
+            capacity,
+            owner: thread::current().id(),
+        }
+    }
+
+    /// Queues `closure`, returning `true` if the queue needs to be scheduled for draining. Unless
+    /// `droppable`, the closure is never dropped to make room for others. Closures are queued
+    /// anyway once only such closures are left to drop.
+    pub fn push(&self, closure: Dispatched<T>, droppable: bool) -> WVResult<bool> {
+        let mut state = self.state.lock().map_err(|_| Error::Poisoned)?;
+
+        if let Some((capacity, overflow)) = self.capacity {
+            while !state.closed && state.closures.len() >= capacity.max(1) {
+                match overflow {
+                    Overflow::Block if thread::current().id() != self.owner => {
+                        state = self.room.wait(state).map_err(|_| Error::Poisoned)?;
+                    },
+                    Overflow::Block => break,
+                    Overflow::DropOldest => match state.closures.iter().position(|(_, droppable)| *droppable) {
+                        Some(i) => {
+                            state.closures.remove(i);
+                        },
+                        None => break,
+                    },
+                    Overflow::Error => return Err(Error::QueueFull),
+                }
+            }
+        }
+
+        if state.closed {
+            return Err(Error::Dispatch);
+        }
+
+        state.closures.push_back((closure, droppable));
+
+        Ok(!std::mem::replace(&mut state.scheduled, true))
+    }
+
+    /// Takes the next closure.
+    pub fn pop(&self) -> Option<Dispatched<T>> {
+        let closure = self.state.lock().ok()?.closures.pop_front().map(|(closure, _)| closure);
+
+        self.room.notify_all();
+        closure
+    }
+
+    /// Ends draining the queue, returning `true` if closures remain and it must be scheduled
+    /// again.
+    pub fn reschedule(&self) -> bool {
+        match self.state.lock() {
+            Ok(mut state) => {
+                state.scheduled = !state.closures.is_empty();
+                state.scheduled
+            },
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the number of queued closures.
+    pub fn len(&self) -> usize {
+        self.state.lock().map(|state| state.closures.len()).unwrap_or(0)
+    }
+
+    /// Drops the queued closures and wakes up blocked senders, which then fail with
+    /// [`Error::Dispatch`].
+    ///
+    /// [`Error::Dispatch`]: enum.Error.html#variant.Dispatch
+    pub fn close(&self) {
+        let closures = match self.state.lock() {
+            Ok(mut state) => {
+                state.closed = true;
+                state.closures.drain(..).collect()
+            },
+            Err(_) => Vec::new(),
+        };
+
+        self.room.notify_all();
+
+        // Closures may own handles and senders, so drop them without holding the lock
+        drop(closures);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn noop<T>() -> Dispatched<T> {
+        Box::new(|_| Ok(()))
+    }
+
+    #[test]
+    fn test_schedule() {
+        let queue = DispatchQueue::<()>::new(None);
+
+        assert!(queue.push(noop(), true).unwrap());
+        assert!(!queue.push(noop(), true).unwrap());
+        assert!(queue.pop().is_some());
+        assert!(queue.reschedule());
+        assert!(!queue.push(noop(), true).unwrap());
+        assert!(queue.pop().is_some());
+        assert!(queue.pop().is_some());
+        assert!(queue.pop().is_none());
+        assert!(!queue.reschedule());
+        assert!(queue.push(noop(), true).unwrap());
+    }
+
+    #[test]
+    fn test_overflow() {
+        let queue = DispatchQueue::<()>::new(Some((2, Overflow::Error)));
+
+        queue.push(noop(), true).unwrap();
+        queue.push(noop(), true).unwrap();
+
+        match queue.push(noop(), true) {
+            Err(Error::QueueFull) => (),
+            r => panic!("Unexpected result {:?}", r),
+        }
+
+        let queue = DispatchQueue::<()>::new(Some((2, Overflow::DropOldest)));
+        let (first, second) = (Arc::new(()), Arc::new(()));
+        let (a, b) = (first.clone(), second.clone());
+
+        queue.push(Box::new(move |_| { drop(a); Ok(()) }), true).unwrap();
+        queue.push(Box::new(move |_| { drop(b); Ok(()) }), true).unwrap();
+        queue.push(noop(), true).unwrap();
+
+        assert_eq!(2, queue.len());
+        assert_eq!(1, Arc::strong_count(&first));
+        assert_eq!(2, Arc::strong_count(&second));
+
+        // Closures which are not droppable are skipped, and queued even without room
+        let queue = DispatchQueue::<()>::new(Some((2, Overflow::DropOldest)));
+        let kept = Arc::new(());
+        let k = kept.clone();
+
+        queue.push(Box::new(move |_| { drop(k); Ok(()) }), false).unwrap();
+        queue.push(noop(), true).unwrap();
+        queue.push(noop(), true).unwrap();
+        queue.push(noop(), false).unwrap();
+        assert_eq!(2, queue.len());
+
+        queue.push(noop(), true).unwrap();
+        assert_eq!(3, queue.len());
+        assert_eq!(2, Arc::strong_count(&kept));
+    }
+
+    #[test]
+    fn test_block() {
+        let queue = Arc::new(DispatchQueue::<()>::new(Some((1, Overflow::Block))));
+
+        queue.push(noop(), true).unwrap();
+
+        // The owning thread never blocks
+        queue.push(noop(), true).unwrap();
+        assert_eq!(2, queue.len());
+
+        let sender = {
+            let queue = queue.clone();
+            thread::spawn(move || queue.push(noop(), true))
+        };
+
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(2, queue.len());
+
+        queue.pop();
+        queue.pop();
+        assert!(sender.join().unwrap().is_ok());
+        assert_eq!(1, queue.len());
+
+        let sender = {
+            let queue = queue.clone();
+            thread::spawn(move || queue.push(noop(), true))
+        };
+
+        thread::sleep(Duration::from_millis(50));
+        queue.close();
+
+        match sender.join().unwrap() {
+            Err(Error::Dispatch) => (),
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+}
@@ -2,6 +2,7 @@ use std::{
     error,
     ffi::NulError,
     fmt::{self, Debug, Display},
+    io,
 };
 
 pub trait CustomError: Display + Debug + Send + Sync + 'static {}
@@ -19,6 +20,11 @@ pub enum Error {
     NulByte(NulError),
     /// An error occurred while evaluating JavaScript in a WebView instance.
     JsEvaluation,
+    /// JavaScript evaluated in a WebView instance threw an exception.
+    JsException {
+        message: String,
+        stack: Option<String>,
+    },
     /// An error occurred while injecting CSS into a WebView instance.
     CssInjection,
     /// Failure to dispatch a closure to a WebView instance via a handle, likely because the
@@ -26,8 +32,57 @@ pub enum Error {
     Dispatch,
     /// WebViewStateData lock was poisoned
     Poisoned,
+    /// The dispatch queue is full. See [`WebViewBuilder::dispatch_queue`].
+    ///
+    /// [`WebViewBuilder::dispatch_queue`]: struct.WebViewBuilder.html#method.dispatch_queue
+    QueueFull,
+    /// The page was unloaded before the result of an expression evaluated with
+    /// [`WebView::eval_with_result`] was available.
+    ///
+    /// [`WebView::eval_with_result`]: struct.WebView.html#method.eval_with_result
+    Unloaded,
+    /// The result was not available in time. See [`Handle::eval_sync_timeout`].
+    ///
+    /// [`Handle::eval_sync_timeout`]: struct.Handle.html#method.eval_sync_timeout
+    Timeout,
+    /// JavaScript sent a message larger than the limit set with
+    /// [`WebViewBuilder::max_message_size`], which was dropped.
+    ///
+    /// [`WebViewBuilder::max_message_size`]: struct.WebViewBuilder.html#method.max_message_size
+    MessageTooLarge {
+        size: usize,
+        limit: usize,
+    },
+    /// A value could not be serialized to or deserialized from JSON.
+    Json(serde_json::Error),
+    /// The feature is not supported on this platform.
+    Unsupported(&'static str),
+    /// An I/O error occurred, e.g. while resolving the path of [`Content::File`].
+    ///
+    /// [`Content::File`]: enum.Content.html#variant.File
+    Io(io::Error),
+    /// JavaScript called a command which has not been registered.
+    UnknownCommand(String),
+    /// The arguments JavaScript passed to a command could not be deserialized.
+    InvalidArguments {
+        command: String,
+        cause: serde_json::Error,
+    },
+    /// The page is not allowed to call the command by the allowlist set with
+    /// [`WebViewBuilder::allow`].
+    ///
+    /// [`WebViewBuilder::allow`]: struct.WebViewBuilder.html#method.allow
+    Forbidden(String),
+    /// A handler panicked. Produced by the [`catch_panic`] middleware.
+    ///
+    /// [`catch_panic`]: fn.catch_panic.html
+    Panic(String),
     /// An user-specified error occurred. For use inside invoke and dispatch closures.
     Custom(Box<CustomError>),
+    /// An error which ends [`run()`] even when handler errors are forwarded to JavaScript.
+    ///
+    /// [`run()`]: struct.WebView.html#method.run
+    Fatal(Box<Error>),
 }
 
 impl Error {
@@ -35,12 +90,62 @@ impl Error {
     pub fn custom<E: CustomError>(error: E) -> Error {
         Error::Custom(Box::new(error))
     }
+
+    /// Marks `error` as fatal, so that it ends [`run()`] even when handler errors are forwarded
+    /// to JavaScript.
+    ///
+    /// [`run()`]: struct.WebView.html#method.run
+    pub fn fatal<E: Into<Error>>(error: E) -> Error {
+        match error.into() {
+            Error::Fatal(e) => Error::Fatal(e),
+            e => Error::Fatal(Box::new(e)),
+        }
+    }
+
+    /// Returns `true` if the error was created with [`Error::fatal`].
+    ///
+    /// [`Error::fatal`]: enum.Error.html#method.fatal
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, Error::Fatal(_))
+    }
+
+    /// Returns the name of the variant, e.g. `"UnknownCommand"`. Fatal errors are named after the
+    /// error they wrap.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Error::UninitializedField(_) => "UninitializedField",
+            Error::Initialization => "Initialization",
+            Error::NulByte(_) => "NulByte",
+            Error::JsEvaluation => "JsEvaluation",
+            Error::JsException { .. } => "JsException",
+            Error::CssInjection => "CssInjection",
+            Error::Dispatch => "Dispatch",
+            Error::Poisoned => "Poisoned",
+            Error::QueueFull => "QueueFull",
+            Error::Unloaded => "Unloaded",
+            Error::Timeout => "Timeout",
+            Error::MessageTooLarge { .. } => "MessageTooLarge",
+            Error::Json(_) => "Json",
+            Error::Unsupported(_) => "Unsupported",
+            Error::Io(_) => "Io",
+            Error::UnknownCommand(_) => "UnknownCommand",
+            Error::InvalidArguments { .. } => "InvalidArguments",
+            Error::Forbidden(_) => "Forbidden",
+            Error::Panic(_) => "Panic",
+            Error::Custom(_) => "Custom",
+            Error::Fatal(e) => e.name(),
+        }
+    }
 }
 
 impl error::Error for Error {
     fn cause(&self) -> Option<&error::Error> {
         match self {
             Error::NulByte(cause) => Some(cause),
+            Error::Json(cause) => Some(cause),
+            Error::Io(cause) => Some(cause),
+            Error::InvalidArguments { cause, .. } => Some(cause),
+            Error::Fatal(cause) => Some(cause),
             _ => None,
         }
     }
@@ -49,6 +154,10 @@ impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
             Error::NulByte(ref cause) => Some(cause),
+            Error::Json(ref cause) => Some(cause),
+            Error::Io(ref cause) => Some(cause),
+            Error::InvalidArguments { ref cause, .. } => Some(cause),
+            Error::Fatal(ref cause) => Some(cause),
             _ => None,
         }
     }
@@ -63,13 +172,30 @@ impl Display for Error {
             Error::Initialization => write!(f, "Webview failed to initialize."),
             Error::NulByte(cause) => write!(f, "{}", cause),
             Error::JsEvaluation => write!(f, "Failed to evaluate JavaScript."),
+            Error::JsException { message, .. } => write!(f, "JavaScript exception: {}", message),
             Error::CssInjection => write!(f, "Failed to inject CSS."),
             Error::Poisoned => write!(f, "WebViewStateData lock was poisoned"),
+            Error::QueueFull => write!(f, "Dispatch queue is full."),
+            Error::Unloaded => write!(f, "The page was unloaded before the result was available."),
+            Error::Timeout => write!(f, "The result was not available in time."),
+            Error::MessageTooLarge { size, limit } => {
+                write!(f, "Message of {} bytes exceeds the limit of {} bytes.", size, limit)
+            }
+            Error::Json(cause) => write!(f, "{}", cause),
+            Error::Unsupported(feature) => write!(f, "Not supported on this platform: {}.", feature),
+            Error::Io(cause) => write!(f, "{}", cause),
+            Error::UnknownCommand(command) => write!(f, "Unknown command: {}.", command),
+            Error::InvalidArguments { command, cause } => {
+                write!(f, "Invalid arguments for command {}: {}", command, cause)
+            }
             Error::Dispatch => write!(
                 f,
                 "Closure could not be dispatched. WebView was likely dropped."
             ),
+            Error::Forbidden(command) => write!(f, "Command not allowed from this page: {}.", command),
+            Error::Panic(message) => write!(f, "Handler panicked: {}", message),
             Error::Custom(e) => write!(f, "Error: {}", e),
+            Error::Fatal(e) => write!(f, "{}", e),
         }
     }
 }
@@ -82,3 +208,15 @@ impl From<NulError> for Error {
         Error::NulByte(e)
     }
 }
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Error {
+        Error::Json(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
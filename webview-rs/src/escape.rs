@@ -1,41 +1,177 @@
+use crate::error::WVResult;
+use serde::Serialize;
+use serde_json::ser::{
+    CharEscape,
+    Formatter,
+    Serializer,
+};
+use std::char;
 use std::fmt::{self, Write};
+use std::io;
 
 /// Escape a string to pass it into JavaScript.
 ///
 /// # Example
 ///
 /// ```rust,no_run
-/// # use web_view::WebView;
-/// # use std::mem;
-/// #
-/// # let mut view: WebView<()> = unsafe { mem::uninitialized() };
+/// # use webview::{WebView, WVResult};
 /// #
+/// # fn run(view: &mut WebView<()>) -> WVResult {
 /// let string = "Hello, world!";
 ///
 /// // Calls the function callback with "Hello, world!" as its parameter.
 ///
-/// view.eval(&format!("callback({});", web_view::escape(string)));
+/// view.eval(&format!("callback({});", webview::escape(string)))
+/// # }
 /// ```
-pub fn escape(string: &str) -> Escaper {
+pub fn escape(string: &str) -> Escaper<'_> {
     Escaper(string)
 }
 
+/// Escape a UTF-16 string, which may contain lone surrogates, to pass it into JavaScript.
+///
+/// Unlike a `str`, strings from JavaScript or Windows APIs are not guaranteed to be valid
+/// Unicode; lone surrogates are preserved as `\u` escapes.
+pub fn escape_utf16(units: &[u16]) -> String {
+    let mut escaped = String::from("'");
+
+    for c in char::decode_utf16(units.iter().cloned()) {
+        match c {
+            Ok(c) => {
+                let _ = write_char(&mut escaped, c);
+            },
+            Err(e) => {
+                let _ = write!(escaped, "\\u{:04X}", e.unpaired_surrogate());
+            },
+        }
+    }
+
+    escaped.push('\'');
+    escaped
+}
+
+/// Serializes `value` into a JavaScript expression which can be safely interpolated into code
+/// passed to `eval`, or into an inline `<script>` element.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use webview::{WebView, WVResult};
+/// #
+/// # fn run(view: &mut WebView<()>) -> WVResult {
+/// let tasks = vec!["</script><script>alert('injected')</script>"];
+///
+/// view.eval(&format!("render({});", webview::to_js(&tasks)?))
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::Json`] if `value` cannot be serialized.
+///
+/// [`Error::Json`]: enum.Error.html#variant.Json
+pub fn to_js<T: Serialize + ?Sized>(value: &T) -> WVResult<String> {
+    let mut js = Vec::new();
+
+    value.serialize(&mut Serializer::with_formatter(&mut js, JsFormatter))?;
+
+    // The formatter only ever writes valid UTF-8
+    Ok(String::from_utf8(js).expect("serialized JavaScript is not UTF-8"))
+}
+
+/// Formats JavaScript code, serializing each interpolated value with [`to_js`].
+///
+/// Evaluates to a `WVResult<String>`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use webview::{js, WebView, WVResult};
+/// #
+/// # fn run(view: &mut WebView<()>) -> WVResult {
+/// let name = "Robert'); alert('injected";
+///
+/// view.eval(&js!("greet({}, {})", name, 3)?)
+/// # }
+/// ```
+///
+/// [`to_js`]: fn.to_js.html
+#[macro_export]
+macro_rules! js {
+    ($fmt:literal $(, $arg:expr)* $(,)*) => {
+        (|| -> $crate::WVResult<::std::string::String> {
+            Ok(format!($fmt $(, $crate::to_js(&$arg)?)*))
+        })()
+    };
+}
+
+/// JSON formatter additionally escaping characters which are unsafe within JavaScript source or
+/// HTML `<script>` elements.
+struct JsFormatter;
+
+impl Formatter for JsFormatter {
+    fn write_string_fragment<W: ?Sized + io::Write>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()> {
+        let mut start = 0;
+
+        for (i, c) in fragment.char_indices() {
+            let escaped = match c {
+                '<' => "\\u003C",
+                '>' => "\\u003E",
+                '&' => "\\u0026",
+                '\u{2028}' => "\\u2028",
+                '\u{2029}' => "\\u2029",
+                _ => continue,
+            };
+
+            writer.write_all(&fragment.as_bytes()[start..i])?;
+            writer.write_all(escaped.as_bytes())?;
+            start = i + c.len_utf8();
+        }
+
+        writer.write_all(&fragment.as_bytes()[start..])
+    }
+
+    fn write_char_escape<W: ?Sized + io::Write>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()> {
+        // Identical to JSON, which is a subset of JavaScript once U+2028 and U+2029 are escaped
+        serde_json::ser::CompactFormatter.write_char_escape(writer, char_escape)
+    }
+}
+
 // "All code points may appear literally in a string literal except for the
 // closing quote code points, U+005C (REVERSE SOLIDUS), U+000D (CARRIAGE
 // RETURN), U+2028 (LINE SEPARATOR), U+2029 (PARAGRAPH SEPARATOR), and U+000A
 // (LINE FEED)." - ES6 Specification
+//
+// NUL would truncate the string passed to webview, and `<` could close an
+// enclosing `<script>` element.
 
 pub struct Escaper<'a>(&'a str);
 
 const SPECIAL: &[char] = &[
+    '\0',       // U+0000 (NULL)
     '\n',       // U+000A (LINE FEED)
     '\r',       // U+000D (CARRIAGE RETURN)
     '\'',       // U+0027 (APOSTROPHE)
+    '<',        // U+003C (LESS-THAN SIGN)
     '\\',       // U+005C (REVERSE SOLIDUS)
     '\u{2028}', // U+2028 (LINE SEPARATOR)
     '\u{2029}', // U+2029 (PARAGRAPH SEPARATOR)
 ];
 
+fn write_char<W: Write>(f: &mut W, c: char) -> fmt::Result {
+    match c {
+        '\0' => f.write_str("\\u0000"),
+        '\n' => f.write_str("\\n"),
+        '\r' => f.write_str("\\r"),
+        '\'' => f.write_str("\\'"),
+        '<' => f.write_str("\\u003C"),
+        '\\' => f.write_str("\\\\"),
+        '\u{2028}' => f.write_str("\\u2028"),
+        '\u{2029}' => f.write_str("\\u2029"),
+        c => f.write_char(c),
+    }
+}
+
 impl<'a> fmt::Display for Escaper<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let &Escaper(mut string) = self;
@@ -50,15 +186,7 @@ impl<'a> fmt::Display for Escaper<'a> {
 
                 let mut chars = string[i..].chars();
 
-                f.write_str(match chars.next().unwrap() {
-                    '\n' => "\\n",
-                    '\r' => "\\r",
-                    '\'' => "\\'",
-                    '\\' => "\\\\",
-                    '\u{2028}' => "\\u2028",
-                    '\u{2029}' => "\\u2029",
-                    _ => unreachable!(),
-                })?;
+                write_char(f, chars.next().unwrap())?;
 
                 string = chars.as_str();
             } else {
@@ -73,9 +201,45 @@ impl<'a> fmt::Display for Escaper<'a> {
     }
 }
 
-#[test]
-fn test() {
-    let plain = "ABC \n\r' abc \\  \u{2028}   \u{2029}123";
-    let escaped = escape(plain).to_string();
-    assert!(escaped == "'ABC \\n\\r\\' abc \\\\  \\u2028   \\u2029123'");
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test() {
+        let plain = "ABC \n\r' abc \\  \u{2028}   \u{2029}123";
+        let escaped = escape(plain).to_string();
+        assert!(escaped == "'ABC \\n\\r\\' abc \\\\  \\u2028   \\u2029123'");
+    }
+
+    #[test]
+    fn test_escape_script() {
+        assert_eq!("'\\u003C/script>\\u0000'", escape("</script>\0").to_string());
+    }
+
+    #[test]
+    fn test_escape_utf16() {
+        let units: Vec<u16> = "a'\u{1F600}".encode_utf16().chain(vec![0xD800, 0x62]).collect();
+
+        assert_eq!("'a\\'\u{1F600}\\uD800b'", escape_utf16(&units));
+    }
+
+    #[test]
+    fn test_to_js() {
+        let value = vec!["</script>", "\u{2028}\u{2029}", "\"\\\0", "a & b"];
+
+        assert_eq!(
+            r#"["\u003C/script\u003E","\u2028\u2029","\"\\\u0000","a \u0026 b"]"#,
+            to_js(&value).unwrap(),
+        );
+        assert_eq!("null", to_js(&()).unwrap());
+        assert_eq!("1.5", to_js(&1.5).unwrap());
+    }
+
+    #[test]
+    fn test_js_macro() {
+        let name = "'); alert('injected";
+
+        assert_eq!(r#"greet("'); alert('injected", 3)"#, js!("greet({}, {})", name, 3).unwrap());
+    }
 }
@@ -1,3 +1,16 @@
+use crate::bridge::{
+    self,
+    Outgoing,
+};
+use crate::batch::{
+    Batch,
+    Flush,
+};
+use crate::channel::Sender;
+use crate::dispatch::{
+    DispatchQueue,
+    Dispatched,
+};
 use crate::{
     ffi_dispatch_handler,
     WebView,
@@ -8,12 +21,18 @@ use crate::error::{
     Error,
     WVResult,
 };
-use std::marker::PhantomData;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::ptr;
+use std::sync::mpsc;
 use std::sync::{
     Arc,
+    Mutex,
     RwLock,
     Weak,
 };
+use std::time::Duration;
 
 
 /// A thread-safe handle to a [`WebView`] instance. Used to dispatch closures onto its task queue.
@@ -22,15 +41,17 @@ use std::sync::{
 pub struct Handle<T> {
     internal: *mut WebViewFFI,
     live: Weak<RwLock<()>>,
-    _phantom: PhantomData<T>,
+    queue: Arc<DispatchQueue<T>>,
+    batch: Arc<Mutex<Batch>>,
 }
 
 impl<T> Handle<T> {
-    pub fn new(internal: *mut WebViewFFI, live: Arc<RwLock<()>>) -> Self {
+    pub fn new(internal: *mut WebViewFFI, live: Arc<RwLock<()>>, queue: Arc<DispatchQueue<T>>, batch: Arc<Mutex<Batch>>) -> Self {
         Self {
             internal,
             live: Arc::downgrade(&live),
-            _phantom: PhantomData,
+            queue,
+            batch,
         }
     }
 
@@ -38,35 +59,272 @@ impl<T> Handle<T> {
     ///
     /// # Errors
     ///
-    /// Returns [`Error::Dispatch`] if the [`WebView`] has been dropped.
+    /// Returns [`Error::Dispatch`] if the [`WebView`] has been dropped, or [`Error::QueueFull`] if
+    /// the dispatch queue is full and set to [`Overflow::Error`] (see
+    /// [`WebViewBuilder::dispatch_queue`]).
     ///
     /// If the closure returns an `Err`, it will be returned on the next call to [`step()`].
     ///
     /// [`WebView`]: struct.WebView.html
     /// [`Error::Dispatch`]: enum.Error.html#variant.Dispatch
+    /// [`Error::QueueFull`]: enum.Error.html#variant.QueueFull
+    /// [`Overflow::Error`]: enum.Overflow.html#variant.Error
+    /// [`WebViewBuilder::dispatch_queue`]: struct.WebViewBuilder.html#method.dispatch_queue
     /// [`step()`]: struct.WebView.html#method.step
     pub fn dispatch<F>(&self, func: F) -> WVResult
     where
         F: FnOnce(&mut WebView<T>) -> WVResult + Send + 'static,
     {
-        let lock = self.live
-            .upgrade()
-            .ok_or(Error::Dispatch)?;
-        let _locked = lock
-            .read()
-            .map_err(|_| Error::Dispatch)?;
-        let closure = Box::new(func);
-
-        unsafe {
-            webview_dispatch(
-                self.internal,
-                Some(ffi_dispatch_handler::<F, T>),
-                Box::into_raw(closure) as _,
-            )
+        self.push(Box::new(func), true)
+    }
+
+    /// Queues `closure` like [`dispatch()`], only letting [`Overflow::DropOldest`] drop it if
+    /// `droppable`.
+    ///
+    /// [`dispatch()`]: struct.Handle.html#method.dispatch
+    /// [`Overflow::DropOldest`]: enum.Overflow.html#variant.DropOldest
+    fn push(&self, closure: Dispatched<T>, droppable: bool) -> WVResult {
+        // Wait for room, if need be, before checking that the WebView is alive
+        let schedule = self.queue.push(closure, droppable)?;
+
+        if schedule {
+            let lock = self.live
+                .upgrade()
+                .ok_or(Error::Dispatch)?;
+            let _locked = lock
+                .read()
+                .map_err(|_| Error::Dispatch)?;
+
+            unsafe {
+                webview_dispatch(
+                    self.internal,
+                    Some(ffi_dispatch_handler::<T>),
+                    ptr::null_mut(),
+                )
+            }
         }
 
         Ok(())
     }
+
+    /// Emits `event` to the listeners added in JavaScript with `rust.on(event, listener)`, passing
+    /// them `payload`.
+    ///
+    /// `payload` is serialized on the calling thread.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Json`] if `payload` cannot be serialized, or [`Error::Dispatch`] if the
+    /// [`WebView`] has been dropped.
+    ///
+    /// [`WebView`]: struct.WebView.html
+    /// [`Error::Json`]: enum.Error.html#variant.Json
+    /// [`Error::Dispatch`]: enum.Error.html#variant.Dispatch
+    pub fn emit<P: Serialize>(&self, event: &str, payload: P) -> WVResult {
+        let event = event.to_string();
+        let payload = serde_json::to_value(payload)?;
+
+        self.dispatch(move |webview| {
+            bridge::send(webview, &Outgoing::Emit { event: &event, payload: &payload })
+        })
+    }
+
+    /// Evaluates the JavaScript expression `js` on the [`WebView`] thread, blocking until its
+    /// result is available. See [`WebView::eval_with_result()`].
+    ///
+    /// This must not be called from the [`WebView`] thread itself, such as from a dispatched
+    /// closure or an invoke handler, as it would never return.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Dispatch`] if the [`WebView`] has been dropped before the result was
+    /// available, [`Error::Unloaded`] if the page was replaced before, [`Error::JsException`] if the
+    /// expression throws or its promise is rejected, and [`Error::Json`] if the result cannot be
+    /// deserialized into `R`. Use [`eval_sync_timeout()`] for expressions which may never settle.
+    ///
+    /// [`WebView`]: struct.WebView.html
+    /// [`WebView::eval_with_result()`]: struct.WebView.html#method.eval_with_result
+    /// [`Error::Dispatch`]: enum.Error.html#variant.Dispatch
+    /// [`Error::Unloaded`]: enum.Error.html#variant.Unloaded
+    /// [`Error::JsException`]: enum.Error.html#variant.JsException
+    /// [`Error::Json`]: enum.Error.html#variant.Json
+    /// [`eval_sync_timeout()`]: struct.Handle.html#method.eval_sync_timeout
+    pub fn eval_sync<R: DeserializeOwned>(&self, js: &str) -> WVResult<R> {
+        self.evaluate(js, None)
+    }
+
+    /// Evaluates the JavaScript expression `js` like [`eval_sync()`], giving up after `timeout`,
+    /// e.g. if the expression returns a promise which never settles.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Timeout`] if the result was not available in time, or the errors of
+    /// [`eval_sync()`].
+    ///
+    /// [`eval_sync()`]: struct.Handle.html#method.eval_sync
+    /// [`Error::Timeout`]: enum.Error.html#variant.Timeout
+    pub fn eval_sync_timeout<R: DeserializeOwned>(&self, js: &str, timeout: Duration) -> WVResult<R> {
+        self.evaluate(js, Some(timeout))
+    }
+
+    fn evaluate<R: DeserializeOwned>(&self, js: &str, timeout: Option<Duration>) -> WVResult<R> {
+        let (sender, receiver) = mpsc::channel::<WVResult<Value>>();
+        let js = js.to_string();
+
+        self.dispatch(move |webview| {
+            let callback_sender = sender.clone();
+            let evaluated = webview.eval_with_result(&js, move |_, result| {
+                let _ = callback_sender.send(result);
+                Ok(())
+            });
+
+            if let Err(e) = evaluated {
+                let _ = sender.send(Err(e));
+            }
+
+            Ok(())
+        })?;
+
+        let value = match timeout {
+            Some(timeout) => receiver.recv_timeout(timeout).map_err(|e| match e {
+                mpsc::RecvTimeoutError::Timeout => Error::Timeout,
+                mpsc::RecvTimeoutError::Disconnected => Error::Dispatch,
+            })??,
+            None => receiver.recv().map_err(|_| Error::Dispatch)??,
+        };
+
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Queues `js` to be evaluated on the [`WebView`] thread, by a single dispatched closure together
+    /// with every other script queued through any handle to the same [`WebView`] before it runs.
+    ///
+    /// Only one closure is dispatched per batch, so producers updating the page many times per
+    /// second do not flood the [`WebView`] thread. Scripts are evaluated in the order they were
+    /// queued, each as if passed to [`WebView::eval`]; one throwing or failing to parse does not
+    /// prevent the others from running.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Dispatch`] if the [`WebView`] has been dropped.
+    ///
+    /// [`WebView`]: struct.WebView.html
+    /// [`WebView::eval`]: struct.WebView.html#method.eval
+    /// [`Error::Dispatch`]: enum.Error.html#variant.Dispatch
+    pub fn eval_batched(&self, js: &str) -> WVResult {
+        self.queue(None, js.to_string())
+    }
+
+    /// Queues `js` like [`eval_batched()`], replacing any script queued with the same `key` which
+    /// has not been evaluated yet, so that only the latest update is applied.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use webview::*;
+    /// #
+    /// # fn run(handle: Handle<()>) -> WVResult {
+    /// for frame in 0..1000 {
+    ///     // The page only ever renders the latest progress it has not caught up with
+    ///     handle.eval_keyed("progress", &js!("setProgress({})", frame)?)?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Dispatch`] if the [`WebView`] has been dropped.
+    ///
+    /// [`eval_batched()`]: struct.Handle.html#method.eval_batched
+    /// [`WebView`]: struct.WebView.html
+    /// [`Error::Dispatch`]: enum.Error.html#variant.Dispatch
+    pub fn eval_keyed(&self, key: &str, js: &str) -> WVResult {
+        self.queue(Some(format!("eval:{}", key)), js.to_string())
+    }
+
+    /// Emits `event` like [`emit()`], as part of the next batch (see [`eval_batched()`]). Only the
+    /// latest payload queued for `event` is emitted.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Json`] if `payload` cannot be serialized, or [`Error::Dispatch`] if the
+    /// [`WebView`] has been dropped.
+    ///
+    /// [`emit()`]: struct.Handle.html#method.emit
+    /// [`eval_batched()`]: struct.Handle.html#method.eval_batched
+    /// [`WebView`]: struct.WebView.html
+    /// [`Error::Json`]: enum.Error.html#variant.Json
+    /// [`Error::Dispatch`]: enum.Error.html#variant.Dispatch
+    pub fn emit_latest<P: Serialize>(&self, event: &str, payload: P) -> WVResult {
+        let payload = serde_json::to_value(payload)?;
+        let js = bridge::script(&Outgoing::Emit { event, payload: &payload })?;
+
+        self.queue(Some(format!("emit:{}", event)), js)
+    }
+
+    /// Adds `js` to the batch, dispatching a closure to evaluate it unless one is pending.
+    fn queue(&self, key: Option<String>, js: String) -> WVResult {
+        let schedule = self.batch
+            .lock()
+            .map_err(|_| Error::Poisoned)?
+            .push(key, js);
+
+        if !schedule {
+            return Ok(());
+        }
+
+        let flush = Flush::new(self.batch.clone());
+
+        // Dropping the closure would leave the batch waiting for the next script queued
+        self.push(Box::new(move |webview| flush.run(webview)), false)
+    }
+
+    /// Opens a channel streaming items of type `P` to JavaScript, where it is consumed with
+    /// `rust.channel(name)`.
+    ///
+    /// In JavaScript, the channel is an async iterator which ends when the sender is closed or
+    /// dropped, and throws if it is ended with [`Sender::error()`]; `stream()` turns it into a
+    /// `ReadableStream`. Items sent before `rust.channel(name)` is called are buffered until then.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::thread;
+    /// # use webview::*;
+    /// #
+    /// # fn run(webview: WebView<()>) -> WVResult {
+    /// // In JavaScript:
+    /// // for await (const line of rust.channel('log')) { console.log(line); }
+    ///
+    /// let mut log = webview.handle().channel("log");
+    ///
+    /// thread::spawn(move || {
+    ///     for i in 0..10 {
+    ///         log.send(format!("Step {}", i))?;
+    ///     }
+    ///
+    ///     log.close()
+    /// });
+    /// # webview.run()
+    /// # }
+    /// ```
+    ///
+    /// [`Sender::error()`]: struct.Sender.html#method.error
+    pub fn channel<P: Serialize>(&self, name: &str) -> Sender<T, P> {
+        Sender::new(self.clone(), name)
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            internal: self.internal,
+            live: self.live.clone(),
+            queue: self.queue.clone(),
+            batch: self.batch.clone(),
+        }
+    }
 }
 
 unsafe impl<T> Send for Handle<T> {}
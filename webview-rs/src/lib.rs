@@ -21,23 +21,104 @@
 // //! [the examples]: https://github.com/Boscop/web-view/tree/master/examples
 // //! [original readme]: https://github.com/zserge/webview/blob/master/README.md
 //
+mod allowlist;
+mod assets;
+mod batch;
+mod bridge;
+mod bytes;
+mod channel;
 mod color;
 mod content;
+mod csp;
+mod deferred;
+mod dispatch;
 mod dialog;
 mod error;
-//mod escape;
+mod escape;
 mod handle;
+mod load;
+mod middleware;
+mod mime;
+mod navigation;
+mod protocol;
+mod reload;
+mod rpc;
+mod secret;
+#[cfg(feature = "server")]
+mod server;
+mod settings;
 mod state_data;
+mod typescript;
 
+pub use allowlist::{
+    Allowlist,
+    Violation,
+};
+use allowlist::Access;
+pub use assets::{
+    embed_assets,
+    Assets,
+};
+use bridge::{
+    Incoming,
+    Outgoing,
+};
 use color::Color;
+pub use bytes::Bytes;
+pub use channel::Sender;
 pub use content::Content;
+pub use deferred::{
+    CancellationToken,
+    Deferred,
+};
+pub use dispatch::Overflow;
+use dispatch::DispatchQueue;
 use dialog::DialogBuilder;
+use middleware::Layer;
 pub use error::{
     Error,
     WVResult,
 };
+pub use escape::{
+    escape,
+    escape_utf16,
+    to_js,
+    Escaper,
+};
 
 pub use handle::Handle;
+pub use load::LoadEvent;
+pub use navigation::{
+    Decision,
+    NavigationKind,
+};
+pub use middleware::{
+    catch_panic,
+    Next,
+};
+pub use protocol::{
+    ProtocolRequest,
+    Response,
+};
+use protocol::ProtocolHandler;
+use reload::Watcher;
+#[cfg(feature = "server")]
+use server::{
+    Server,
+    ServerHandler,
+};
+pub use settings::WebSettings;
+pub use rpc::{
+    call_handler,
+    Request,
+    Router,
+};
+pub use typescript::{
+    Bindings,
+    Declarations,
+    TypeScript,
+};
+use std::collections::HashMap;
 use std::ffi::{
     CStr,
     CString,
@@ -45,13 +126,25 @@ use std::ffi::{
 use std::marker::PhantomData;
 use std::mem::ManuallyDrop;
 use std::os::raw::*;
-use std::ptr::drop_in_place;
+use std::path::Path;
+use std::ptr::{
+    self,
+    drop_in_place,
+};
 use std::sync::{
     Arc,
     RwLock,
 };
-use state_data::WebViewStateData;
-use urlencoding::encode;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use state_data::{
+    DomContentLoadedHandler,
+    InvokeHandler,
+    LoadHandler,
+    NavigationHandler,
+    WebViewStateData,
+};
 use webview_sys::*;
 
 /// Webview instance
@@ -64,6 +157,12 @@ pub struct WebView<T> {
     _phantom: PhantomData<T>
 }
 
+/// Identifies a script added with [`WebView::add_init_script`].
+///
+/// [`WebView::add_init_script`]: struct.WebView.html#method.add_init_script
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ScriptId(u64);
+
 /// Builder for constructing a [`WebView`] instance.
 ///
 /// # Example
@@ -90,20 +189,37 @@ pub struct WebView<T> {
 /// ```
 ///
 /// [`WebView`]: struct.WebView.html
-pub struct WebViewBuilder<'a, T: 'a, I, C: AsRef<str>> {
+pub struct WebViewBuilder<'a, T: 'a, C: AsRef<str>> {
     pub title: &'a str,
     pub content: Option<Content<C>>,
     pub width: i32,
     pub height: i32,
     pub resizable: bool,
     pub debug: bool,
-    pub invoke_handler: Option<I>,
+    pub forward_errors: bool,
+    pub invoke_handler: Option<InvokeHandler<'a, T>>,
+    pub commands: Router<'a, T>,
+    pub middleware: Vec<Layer<'a, T>>,
+    pub allowlist: Allowlist<'a>,
+    pub max_message_size: Option<usize>,
+    pub dispatch_queue: Option<(usize, Overflow)>,
+    pub protocols: HashMap<String, ProtocolHandler<'a>>,
+    pub cors_protocols: Vec<String>,
+    pub init_scripts: Vec<String>,
+    pub load_handler: Option<LoadHandler<'a, T>>,
+    pub dom_content_loaded_handler: Option<DomContentLoadedHandler<'a, T>>,
+    pub navigation_handler: Option<NavigationHandler<'a>>,
+    pub csp: Option<String>,
+    pub settings: WebSettings,
+    #[cfg(feature = "server")]
+    pub serve: bool,
+    #[cfg(feature = "server")]
+    pub server_handler: Option<ServerHandler>,
     pub user_data: Option<T>
 }
 
-impl<'a, T: 'a, I, C> Default for WebViewBuilder<'a, T, I, C>
+impl<'a, T: 'a, C> Default for WebViewBuilder<'a, T, C>
 where
-    I: FnMut(&mut WebView<T>, &str) -> WVResult + 'a,
     C: AsRef<str>,
 {
     fn default() -> Self {
@@ -119,15 +235,32 @@ where
             height: 600,
             resizable: true,
             debug,
+            forward_errors: false,
             invoke_handler: None,
+            commands: Router::new(),
+            middleware: Vec::new(),
+            allowlist: Allowlist::new(),
+            max_message_size: None,
+            dispatch_queue: None,
+            protocols: HashMap::new(),
+            cors_protocols: Vec::new(),
+            init_scripts: Vec::new(),
+            load_handler: None,
+            dom_content_loaded_handler: None,
+            navigation_handler: None,
+            csp: None,
+            settings: WebSettings::default(),
+            #[cfg(feature = "server")]
+            serve: false,
+            #[cfg(feature = "server")]
+            server_handler: None,
             user_data: None,
         }
     }
 }
 
-impl<'a, T: 'a, I, C> WebViewBuilder<'a, T, I, C>
+impl<'a, T: 'a, C> WebViewBuilder<'a, T, C>
 where
-    I: FnMut(&mut WebView<T>, &str) -> WVResult + 'a,
     C: AsRef<str>,
 {
     /// Alias for [`WebViewBuilder::default()`].
@@ -168,24 +301,684 @@ where
         self
     }
 
+    /// Sets the browser settings, such as the user agent or whether pages can run scripts.
+    ///
+    /// # Errors
+    ///
+    /// Settings other than the defaults can only be applied with WebKitGTK. Elsewhere, [`build()`]
+    /// fails with [`Error::Unsupported`].
+    ///
+    /// [`build()`]: struct.WebViewBuilder.html#method.build
+    /// [`Error::Unsupported`]: enum.Error.html#variant.Unsupported
+    pub fn settings(mut self, settings: WebSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
     /// Enables or disables debug mode.
     ///
+    /// In debug mode, the page of [`Content::Dir`] is reloaded whenever a file below its directory
+    /// changes, and that of [`Content::File`] whenever a file next to it does, leaving out
+    /// subdirectories. Hidden files, `node_modules`, and backup and swap files of editors are
+    /// ignored. Stylesheets changed on their own are swapped in with [`inject_css`] instead,
+    /// keeping the state of the page; relative URLs inside them are then resolved against the
+    /// page.
+    ///
     /// Defaults to `true` for debug builds, `false` for release builds.
+    ///
+    /// [`Content::Dir`]: enum.Content.html#variant.Dir
+    /// [`Content::File`]: enum.Content.html#variant.File
+    /// [`inject_css`]: struct.WebView.html#method.inject_css
     pub fn debug(mut self, debug: bool) -> Self {
         self.debug = debug;
         self
     }
 
+    /// Sends errors returned by the invoke handler and commands to JavaScript instead of ending
+    /// [`run()`].
+    ///
+    /// Errors from commands reject the promise returned by `rust.call` either way; other errors are
+    /// emitted as an `error` event, received with `rust.on('error', listener)`. In both cases
+    /// JavaScript receives an `Error` named after the variant, e.g. `UnknownCommand`. Events named
+    /// `error` emitted from Rust are passed on unchanged.
+    ///
+    /// Errors created with [`Error::fatal`] still end [`run()`], and all errors do where the bridge
+    /// script cannot run, see [`build()`].
+    ///
+    /// Defaults to `false`.
+    ///
+    /// [`run()`]: struct.WebView.html#method.run
+    /// [`Error::fatal`]: enum.Error.html#method.fatal
+    /// [`build()`]: struct.WebViewBuilder.html#method.build
+    pub fn forward_errors(mut self, forward_errors: bool) -> Self {
+        self.forward_errors = forward_errors;
+        self
+    }
+
+    /// Adds `js` to the scripts run at the start of every page loaded, before the scripts of the
+    /// page, in the order they were added. Scripts can be added and removed once the WebView has
+    /// been built with [`add_init_script`] and [`remove_init_script`].
+    ///
+    /// Such scripts are only supported by WebKitGTK, see [`build()`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use webview::*;
+    /// #
+    /// # fn build<'a>(builder: WebViewBuilder<'a, (), &'a str>) -> WVResult<WebView<()>> {
+    /// builder
+    ///     .init_script("window.config = { apiVersion: 2 };")
+    ///     .build()
+    /// # }
+    /// ```
+    ///
+    /// [`build()`]: struct.WebViewBuilder.html#method.build
+    /// [`add_init_script`]: struct.WebView.html#method.add_init_script
+    /// [`remove_init_script`]: struct.WebView.html#method.remove_init_script
+    pub fn init_script(mut self, js: &str) -> Self {
+        self.init_scripts.push(js.to_string());
+        self
+    }
+
+    /// Sets `handler` to be called as pages load, with the [`LoadEvent`]s they go through.
+    ///
+    /// Page loads are only reported by WebKitGTK. Elsewhere, [`build()`] fails with
+    /// [`Error::Unsupported`].
+    ///
+    /// Errors returned by `handler` are handled like those of the invoke handler.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use webview::*;
+    /// #
+    /// # fn build<'a>(builder: WebViewBuilder<'a, (), &'a str>) -> WVResult<WebView<()>> {
+    /// builder
+    ///     .on_load(|_webview, event| {
+    ///         if let LoadEvent::Failed { url, error } = event {
+    ///             eprintln!("Failed to load {}: {}", url, error);
+    ///         }
+    ///
+    ///         Ok(())
+    ///     })
+    ///     .build()
+    /// # }
+    /// ```
+    ///
+    /// [`LoadEvent`]: enum.LoadEvent.html
+    /// [`build()`]: struct.WebViewBuilder.html#method.build
+    /// [`Error::Unsupported`]: enum.Error.html#variant.Unsupported
+    pub fn on_load<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut(&mut WebView<T>, &LoadEvent) -> WVResult + 'a,
+    {
+        self.load_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Sets `handler` to be called with the URL of every page whose document has been parsed,
+    /// when its `DOMContentLoaded` event fires. The DOM can then be used from [`eval()`].
+    ///
+    /// `handler` is called at most once per page, with the URL the page was loaded from, whatever
+    /// the page itself dispatches or posts.
+    ///
+    /// Errors returned by `handler` are handled like those of the invoke handler.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use webview::*;
+    /// #
+    /// # fn build<'a>(builder: WebViewBuilder<'a, (), &'a str>) -> WVResult<WebView<()>> {
+    /// builder
+    ///     .on_dom_content_loaded(|webview, _url| {
+    ///         webview.eval("document.body.classList.add('ready')")
+    ///     })
+    ///     .build()
+    /// # }
+    /// ```
+    ///
+    /// [`eval()`]: struct.WebView.html#method.eval
+    pub fn on_dom_content_loaded<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut(&mut WebView<T>, &str) -> WVResult + 'a,
+    {
+        self.dom_content_loaded_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Sets `handler` to decide whether navigating to a URL is allowed, blocked, or opened in the
+    /// default browser instead. It is called with the URL and what started the navigation, also
+    /// for those started from Rust, including loading the content.
+    ///
+    /// Only navigations of the page itself are passed, not those of frames within it. As WebKitGTK
+    /// only tells them apart once a page is answered, pages it loads itself are decided after
+    /// being requested, and blocking them keeps the current page but not the request from being
+    /// made. Pages restored from history, and those of other schemes such as `mailto:`, are
+    /// decided beforehand. Before WebKitGTK 2.40, all navigations are decided beforehand,
+    /// including those of frames.
+    ///
+    /// Navigations can only be intercepted with WebKitGTK. Elsewhere, [`build()`] fails with
+    /// [`Error::Unsupported`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use webview::*;
+    /// #
+    /// # fn build<'a>(builder: WebViewBuilder<'a, (), &'a str>) -> WVResult<WebView<()>> {
+    /// // Keep the documentation in the app, and open other links in the browser
+    /// builder
+    ///     .content(Content::Url("https://docs.example.com/"))
+    ///     .on_navigation(|url, _kind| {
+    ///         if url.starts_with("https://docs.example.com/") {
+    ///             Decision::Allow
+    ///         } else {
+    ///             Decision::OpenInBrowser
+    ///         }
+    ///     })
+    ///     .build()
+    /// # }
+    /// ```
+    ///
+    /// [`build()`]: struct.WebViewBuilder.html#method.build
+    /// [`Error::Unsupported`]: enum.Error.html#variant.Unsupported
+    pub fn on_navigation<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut(&str, NavigationKind) -> Decision + 'a,
+    {
+        self.navigation_handler = Some(Box::new(handler));
+        self
+    }
+
     /// Sets the invoke handler callback. This will be called when a message is received from
     /// JavaScript.
     ///
+    /// If commands have been registered, it is only called for messages which are not calls to one
+    /// of them.
+    ///
     /// # Errors
     ///
     /// If the closure returns an `Err`, it will be returned on the next call to [`step()`].
     ///
     /// [`step()`]: struct.WebView.html#method.step
-    pub fn invoke_handler(mut self, invoke_handler: I) -> Self {
-        self.invoke_handler = Some(invoke_handler);
+    pub fn invoke_handler<I>(mut self, invoke_handler: I) -> Self
+    where
+        I: FnMut(&mut WebView<T>, &str) -> WVResult + 'a,
+    {
+        self.invoke_handler = Some(Box::new(invoke_handler));
+        self
+    }
+
+    /// Registers a command called from JavaScript with `rust.call(name, args)`.
+    ///
+    /// `args` are deserialized into `A`, and the value returned by `handler` resolves the promise
+    /// returned to JavaScript.
+    ///
+    /// # Errors
+    ///
+    /// Arguments which cannot be deserialized into `A` reject the promise with
+    /// [`Error::InvalidArguments`], and calls to commands which were never registered (with no
+    /// invoke handler set) with [`Error::UnknownCommand`].
+    ///
+    /// Like errors returned by `handler`, these will be returned on the next call to [`step()`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// extern crate serde_derive;
+    /// extern crate webview;
+    ///
+    /// use serde_derive::Deserialize;
+    /// use webview::*;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct AddTask {
+    ///     name: String,
+    /// }
+    ///
+    /// fn main() {
+    ///     WebViewBuilder::new()
+    ///         .content(Content::Html("<script>rust.call('addTask', { name: 'Write docs' })</script>"))
+    ///         .user_data(vec![])
+    ///         .command("addTask", |webview, args: AddTask| {
+    ///             let tasks = webview.user_data();
+    ///             let mut tasks = tasks.write().unwrap();
+    ///
+    ///             tasks.push(args.name);
+    ///             Ok(tasks.len())
+    ///         })
+    ///         .build()
+    ///         .unwrap()
+    ///         .run()
+    ///         .unwrap();
+    /// }
+    /// ```
+    ///
+    /// [`Error::InvalidArguments`]: enum.Error.html#variant.InvalidArguments
+    /// [`Error::UnknownCommand`]: enum.Error.html#variant.UnknownCommand
+    /// [`step()`]: struct.WebView.html#method.step
+    pub fn command<A, R, F>(mut self, name: &str, handler: F) -> Self
+    where
+        A: DeserializeOwned,
+        R: Serialize,
+        F: FnMut(&mut WebView<T>, A) -> WVResult<R> + 'a,
+    {
+        self.commands.add(name, handler);
+        self
+    }
+
+    /// Limits the size of messages sent from JavaScript to `limit` bytes.
+    ///
+    /// Larger messages, including those which the bridge script splits into chunks, are dropped
+    /// without being copied, and [`Error::MessageTooLarge`] is returned on the next call to
+    /// [`step()`] (or forwarded to JavaScript, see [`forward_errors`]). Chunks are only held for
+    /// one message at a time, so that memory use stays within the limit. Calls made with
+    /// `rust.call` are rejected in JavaScript with an `Error` named `MessageTooLarge` instead,
+    /// before being sent.
+    ///
+    /// Unlimited by default.
+    ///
+    /// [`Error::MessageTooLarge`]: enum.Error.html#variant.MessageTooLarge
+    /// [`step()`]: struct.WebView.html#method.step
+    /// [`forward_errors`]: struct.WebViewBuilder.html#method.forward_errors
+    pub fn max_message_size(mut self, limit: usize) -> Self {
+        self.max_message_size = Some(limit);
+        self
+    }
+
+    /// Applies the Content Security Policy `policy` to the pages loaded, restricting what they may
+    /// load and run. Page scripts cannot call `eval` or `new Function` unless `script-src` allows
+    /// `'unsafe-eval'`.
+    ///
+    /// The policy is set with a `<meta>` element in [`Content::Html`], and with a header as well
+    /// as a `<meta>` element in HTML pages served from custom URI schemes, [`Content::Dir`],
+    /// [`Content::Assets`] and [`serve()`], along with `X-Content-Type-Options: nosniff`. Pages loaded from
+    /// [`Content::Url`] keep the policy of their server.
+    ///
+    /// The scripts of this library, and those run with [`eval`], are not subject to the policy.
+    /// Stylesheets injected with [`inject_css`] are, and need `style-src 'unsafe-inline'`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use webview::*;
+    /// #
+    /// # fn build<'a>(builder: WebViewBuilder<'a, (), &'a str>, rendered: &'a str) -> WVResult<WebView<()>> {
+    /// // Show rendered user content, without letting it run scripts or reach the network
+    /// builder
+    ///     .content(Content::Html(rendered))
+    ///     .csp("default-src 'none'; img-src data:; style-src 'unsafe-inline'")
+    ///     .build()
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// [`build()`] and [`WebView::navigate`] return [`Error::Unsupported`] for [`Content::File`],
+    /// unless served with [`serve()`], as policies cannot be applied to `file://` URLs.
+    ///
+    /// [`Content::Html`]: enum.Content.html#variant.Html
+    /// [`Content::Assets`]: enum.Content.html#variant.Assets
+    /// [`Content::Url`]: enum.Content.html#variant.Url
+    /// [`Content::File`]: enum.Content.html#variant.File
+    /// [`Content::Dir`]: enum.Content.html#variant.Dir
+    /// [`serve()`]: struct.WebViewBuilder.html#method.serve
+    /// [`eval`]: struct.WebView.html#method.eval
+    /// [`inject_css`]: struct.WebView.html#method.inject_css
+    /// [`build()`]: struct.WebViewBuilder.html#method.build
+    /// [`WebView::navigate`]: struct.WebView.html#method.navigate
+    /// [`Error::Unsupported`]: enum.Error.html#variant.Unsupported
+    pub fn csp(mut self, policy: &str) -> Self {
+        self.csp = Some(policy.to_string());
+        self
+    }
+
+    /// Bounds the queue of closures dispatched through [`Handle`]s to `capacity`, with `overflow`
+    /// deciding what happens to closures dispatched while it is full.
+    ///
+    /// Closures are run in the order they were dispatched, whatever the bound. Note that with
+    /// [`Overflow::DropOldest`], any closure may be dropped, including those settling deferred
+    /// calls or sending channel items, which are then lost, but not the one evaluating batched
+    /// scripts.
+    ///
+    /// Unbounded by default.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use webview::*;
+    /// #
+    /// # fn build<'a>(builder: WebViewBuilder<'a, (), &'a str>) -> WVResult<WebView<()>> {
+    /// // Producers wait for the UI to catch up once 64 updates are queued
+    /// builder
+    ///     .dispatch_queue(64, Overflow::Block)
+    ///     .build()
+    /// # }
+    /// ```
+    ///
+    /// [`Handle`]: struct.Handle.html
+    /// [`Overflow::DropOldest`]: enum.Overflow.html#variant.DropOldest
+    pub fn dispatch_queue(mut self, capacity: usize, overflow: Overflow) -> Self {
+        self.dispatch_queue = Some((capacity, overflow));
+        self
+    }
+
+    /// Answers requests the page makes to the custom URI `scheme` with `handler`, e.g. to
+    /// `fetch("api://localhost/users")` from the backend, or to stream media owned by Rust into
+    /// `<video>` and `<audio>` elements.
+    ///
+    /// Responses are sent with the MIME type guessed from the path requested unless one is set,
+    /// and only hold the range asked for by requests with a `Range` header. See [`Response`]. A
+    /// handler for the scheme of [`Content::Dir`] or [`Content::Assets`] is used instead of the
+    /// content.
+    ///
+    /// Custom URI schemes are only supported by WebKitGTK. Elsewhere, [`build()`] fails with
+    /// [`Error::Unsupported`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use webview::*;
+    /// #
+    /// # fn build<'a>(builder: WebViewBuilder<'a, (), &'a str>) -> WVResult<WebView<()>> {
+    /// let video = std::fs::read("intro.webm")?;
+    ///
+    /// builder
+    ///     .protocol("media", move |request| match request.path().as_str() {
+    ///         "/intro.webm" => Response::new(video.clone()),
+    ///         _ => Response::not_found(),
+    ///     })
+    ///     .build()
+    /// # }
+    /// ```
+    ///
+    /// [`Response`]: struct.Response.html
+    /// [`Content::Dir`]: enum.Content.html#variant.Dir
+    /// [`Content::Assets`]: enum.Content.html#variant.Assets
+    /// [`build()`]: struct.WebViewBuilder.html#method.build
+    /// [`Error::Unsupported`]: enum.Error.html#variant.Unsupported
+    pub fn protocol<H>(mut self, scheme: &str, handler: H) -> Self
+    where
+        H: FnMut(&ProtocolRequest) -> Response + 'a,
+    {
+        self.protocols.insert(scheme.to_string(), Box::new(handler));
+        self
+    }
+
+    /// Enables CORS for the custom URI `scheme` set with [`protocol`], so that pages of other
+    /// origins may request it with `fetch`, subject to the `Access-Control-Allow-Origin` header of
+    /// its responses, and module scripts may be loaded from it.
+    ///
+    /// The scheme of [`Content::Dir`] and [`Content::Assets`] always has CORS enabled, for their
+    /// module scripts. A scheme stays registered with CORS for as long as the process runs.
+    ///
+    /// [`protocol`]: struct.WebViewBuilder.html#method.protocol
+    /// [`Content::Dir`]: enum.Content.html#variant.Dir
+    /// [`Content::Assets`]: enum.Content.html#variant.Assets
+    pub fn cors_protocol(mut self, scheme: &str) -> Self {
+        self.cors_protocols.push(scheme.to_string());
+        self
+    }
+
+    /// Serves the content over HTTP from 127.0.0.1 on an ephemeral port, and points the WebView
+    /// at it, for frontends which do not run from `data:` or `file://` URLs.
+    ///
+    /// [`Content::Html`] is served as the root page, [`Content::File`] and [`Content::Dir`] with
+    /// the files of their directory, and [`Content::Assets`] from the binary. [`Content::Url`] is
+    /// loaded directly. The server stops once the WebView is dropped.
+    ///
+    /// The first URL loaded holds a random token, which the page then keeps in a cookie, so that
+    /// other local processes cannot read from the server. Requests naming another host than
+    /// `127.0.0.1` or `localhost` are refused, so that other sites cannot reach it through DNS
+    /// rebinding.
+    ///
+    /// Requires the `server` feature.
+    ///
+    /// [`Content::Html`]: enum.Content.html#variant.Html
+    /// [`Content::File`]: enum.Content.html#variant.File
+    /// [`Content::Dir`]: enum.Content.html#variant.Dir
+    /// [`Content::Assets`]: enum.Content.html#variant.Assets
+    /// [`Content::Url`]: enum.Content.html#variant.Url
+    #[cfg(feature = "server")]
+    pub fn serve(mut self) -> Self {
+        self.serve = true;
+        self
+    }
+
+    /// Serves the content over HTTP like [`serve`], answering requests for paths not found in
+    /// the content with `handler`, which gives `fetch`-based frontends a same-origin backend.
+    ///
+    /// Unlike handlers registered with [`protocol`], `handler` runs on the threads of the server.
+    ///
+    /// Requires the `server` feature.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use webview::*;
+    /// #
+    /// # fn build<'a>(builder: WebViewBuilder<'a, (), &'a str>) -> WVResult<WebView<()>> {
+    /// builder
+    ///     .serve_with(|request| match request.path().as_str() {
+    ///         "/api/version" => Response::new(&b"\"1.0\""[..]).mime("application/json"),
+    ///         _ => Response::not_found(),
+    ///     })
+    ///     .build()
+    /// # }
+    /// ```
+    ///
+    /// [`serve`]: struct.WebViewBuilder.html#method.serve
+    /// [`protocol`]: struct.WebViewBuilder.html#method.protocol
+    #[cfg(feature = "server")]
+    pub fn serve_with<H>(mut self, handler: H) -> Self
+    where
+        H: Fn(&ProtocolRequest) -> Response + Send + Sync + 'static,
+    {
+        self.serve = true;
+        self.server_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Allows pages whose URL matches `pattern` to call `commands`, where `"*"` allows every
+    /// command as well as messages passed to `external.invoke` directly.
+    ///
+    /// In `pattern`, `*` matches any sequence of characters, e.g. `https://*.example.com/*`, but
+    /// never goes past the host: the scheme and host of a URL, up to the first `/`, `?` or `#`, are
+    /// matched apart from the rest, and `*` does not match `@` in them. Content set with
+    /// [`Content::Html`] has a `data:` URL.
+    ///
+    /// Once any page has been allowed, messages from other pages, and calls to other commands, are
+    /// dropped before reaching middleware, commands or the invoke handler: calls are rejected with
+    /// [`Error::Forbidden`] in JavaScript, and the message is passed to the hook set with
+    /// [`on_violation`]. They are not returned from [`step()`].
+    ///
+    /// The URL checked is that of the top-level page when it was loaded, so a message sent just
+    /// before navigating away is checked against the page which sent it. Messages posted by frames
+    /// within the page are dropped regardless of the allowlist, unless they go through the
+    /// `external.invoke` of a frame of the same origin as the page. Where the platform does not
+    /// report the current URL, the URL of the content the WebView was built with is used.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// extern crate webview;
+    ///
+    /// use webview::*;
+    ///
+    /// fn main() {
+    ///     WebViewBuilder::new()
+    ///         .content(Content::Url("https://app.example.com/"))
+    ///         .user_data(())
+    ///         .command("log", |_webview, line: String| {
+    ///             println!("{}", line);
+    ///             Ok(())
+    ///         })
+    ///         .command("exit", |webview, ()| {
+    ///             webview.terminate();
+    ///             Ok(())
+    ///         })
+    ///         .allow("https://app.example.com/*", &["log", "exit"])
+    ///         .allow("https://help.example.com/*", &["log"])
+    ///         .on_violation(|violation| eprintln!("Rejected {:?}", violation))
+    ///         .build()
+    ///         .unwrap()
+    ///         .run()
+    ///         .unwrap();
+    /// }
+    /// ```
+    ///
+    /// [`Content::Html`]: enum.Content.html#variant.Html
+    /// [`Error::Forbidden`]: enum.Error.html#variant.Forbidden
+    /// [`on_violation`]: struct.WebViewBuilder.html#method.on_violation
+    /// [`step()`]: struct.WebView.html#method.step
+    pub fn allow(mut self, pattern: &str, commands: &[&str]) -> Self {
+        self.allowlist.allow(pattern, commands);
+        self
+    }
+
+    /// Sets a hook called with every message rejected by the allowlist, e.g. for auditing. See
+    /// [`allow`].
+    ///
+    /// [`allow`]: struct.WebViewBuilder.html#method.allow
+    pub fn on_violation<H>(mut self, hook: H) -> Self
+    where
+        H: FnMut(&Violation) + 'a,
+    {
+        self.allowlist.on_violation(hook);
+        self
+    }
+
+    /// Registers a command which settles the call later, typically from a worker thread, through
+    /// the [`Deferred`] it is passed.
+    ///
+    /// Calls made with `rust.call(name, args, { signal })` are cancelled when the `AbortSignal`
+    /// is aborted, and any pending call is cancelled when the page goes away. Workers can check
+    /// [`Deferred::is_cancelled`], or a [`CancellationToken`] from [`Deferred::token`], to stop
+    /// early; settling a cancelled call does nothing. If the command itself returns an `Err`, the
+    /// call is rejected with it and cancelled.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// extern crate webview;
+    ///
+    /// use std::thread;
+    /// use std::time::Duration;
+    /// use webview::*;
+    ///
+    /// fn main() {
+    ///     WebViewBuilder::new()
+    ///         .content(Content::Html(r#"<script>
+    ///             var controller = new AbortController();
+    ///             rust.call('count', 100, { signal: controller.signal }).then(console.log);
+    ///             setTimeout(function() { controller.abort(); }, 1000);
+    ///         </script>"#))
+    ///         .user_data(())
+    ///         .deferred_command("count", |_webview, to: u32, call| {
+    ///             thread::spawn(move || {
+    ///                 for _ in 0..to {
+    ///                     if call.is_cancelled() {
+    ///                         return Ok(());
+    ///                     }
+    ///
+    ///                     thread::sleep(Duration::from_millis(100));
+    ///                 }
+    ///
+    ///                 call.resolve(to)
+    ///             });
+    ///
+    ///             Ok(())
+    ///         })
+    ///         .build()
+    ///         .unwrap()
+    ///         .run()
+    ///         .unwrap();
+    /// }
+    /// ```
+    ///
+    /// [`Deferred`]: struct.Deferred.html
+    /// [`Deferred::is_cancelled`]: struct.Deferred.html#method.is_cancelled
+    /// [`Deferred::token`]: struct.Deferred.html#method.token
+    /// [`CancellationToken`]: struct.CancellationToken.html
+    pub fn deferred_command<A, F>(mut self, name: &str, handler: F) -> Self
+    where
+        A: DeserializeOwned,
+        F: FnMut(&mut WebView<T>, A, Deferred<T>) -> WVResult + 'a,
+    {
+        self.commands.add_deferred(name, handler);
+        self
+    }
+
+    /// Registers a command like [`command`], recording its argument and result types so that
+    /// [`bindings`] can declare it for TypeScript.
+    ///
+    /// Implement [`TypeScript`] for your own argument and result structs with [`ts_interface!`].
+    ///
+    /// [`command`]: struct.WebViewBuilder.html#method.command
+    /// [`bindings`]: struct.WebViewBuilder.html#method.bindings
+    /// [`TypeScript`]: trait.TypeScript.html
+    /// [`ts_interface!`]: macro.ts_interface.html
+    pub fn typed_command<A, R, F>(mut self, name: &str, handler: F) -> Self
+    where
+        A: DeserializeOwned + TypeScript,
+        R: Serialize + TypeScript,
+        F: FnMut(&mut WebView<T>, A) -> WVResult<R> + 'a,
+    {
+        self.commands.add_typed(name, handler);
+        self
+    }
+
+    /// Returns a `.d.ts` file and JavaScript client module for the registered commands, without
+    /// building the WebView.
+    ///
+    /// See [`Bindings`] for an example.
+    ///
+    /// [`Bindings`]: struct.Bindings.html
+    pub fn bindings(&self) -> Bindings {
+        self.commands.bindings()
+    }
+
+    /// Adds a middleware layer around the invoke handler and commands.
+    ///
+    /// Every message sent from JavaScript passes through the layers in the order they were added;
+    /// each layer decides whether to pass it on through [`Next::run`], and sees the result.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// extern crate webview;
+    ///
+    /// use std::time::Instant;
+    /// use webview::*;
+    ///
+    /// fn main() {
+    ///     WebViewBuilder::new()
+    ///         .content(Content::Html("<script>rust.call('ping')</script>"))
+    ///         .user_data(())
+    ///         .middleware(catch_panic())
+    ///         .middleware(|webview, message, next: Next<_>| {
+    ///             let start = Instant::now();
+    ///             let result = next.run(webview, message);
+    ///
+    ///             println!("{} took {:?}", message, start.elapsed());
+    ///             result
+    ///         })
+    ///         .command("ping", |_webview, ()| Ok("pong"))
+    ///         .build()
+    ///         .unwrap()
+    ///         .run()
+    ///         .unwrap();
+    /// }
+    /// ```
+    ///
+    /// [`Next::run`]: struct.Next.html#method.run
+    pub fn middleware<M>(mut self, layer: M) -> Self
+    where
+        M: FnMut(&mut WebView<T>, &str, Next<T>) -> WVResult + 'a,
+    {
+        self.middleware.push(Box::new(layer));
         self
     }
 
@@ -197,6 +990,18 @@ where
     }
 
     /// Validates provided arguments and returns a new WebView if successful.
+    ///
+    /// The bridge script of this library, providing `window.rust`, can only run on every page
+    /// load with WebKitGTK. Elsewhere, pages only have `external.invoke`, messages reach the invoke
+    /// handler as they were posted, and features relying on the bridge are unsupported.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Unsupported`] where the bridge script cannot run, if commands, init
+    /// scripts or a `DOMContentLoaded` handler are set, or where page loads are not reported, if a
+    /// load handler is set.
+    ///
+    /// [`Error::Unsupported`]: enum.Error.html#variant.Unsupported
     pub fn build(self) -> WVResult<WebView<T>> {
         macro_rules! require_field {
             ($name:ident) => {
@@ -208,19 +1013,88 @@ where
         let title = CString::new(self.title)?;
         let content = require_field!(content);
         let user_data = require_field!(user_data);
-        let invoke_handler = require_field!(invoke_handler);
-        let url = match content {
-            Content::Url(url) => CString::new(url.as_ref())?,
-            Content::Html(html) => CString::new(format!("data:text/html,{}", encode(html.as_ref())))?
+        let needs_bridge =
+            !self.commands.is_empty() || !self.init_scripts.is_empty() || self.dom_content_loaded_handler.is_some();
+        let invoke_handler = match (self.commands.is_empty(), self.invoke_handler) {
+            (true, None) => return Err(Error::UninitializedField("invoke_handler")),
+            (true, Some(invoke_handler)) => invoke_handler,
+            (false, invoke_handler) => route(self.commands, invoke_handler),
+        };
+        let invoke_handler = middleware::wrap(invoke_handler, self.middleware);
+        let watched = match (&content, self.debug) {
+            (Content::Dir(path), true) => Some((Path::new(path.as_ref()).canonicalize()?, true)),
+            // Its directory may hold anything, such as a whole project and its build output
+            (Content::File(path), true) => {
+                Path::new(path.as_ref()).canonicalize()?.parent().map(|dir| (dir.to_path_buf(), false))
+            },
+            _ => None,
+        };
+        #[cfg(feature = "server")]
+        let server = match self.serve {
+            true => Server::start(&content, self.server_handler, self.csp.clone())?,
+            false => None,
+        };
+        #[cfg(feature = "server")]
+        let served = server.as_ref().map(|server| server.url().to_string());
+        #[cfg(not(feature = "server"))]
+        let served: Option<String> = None;
+        let url = match served {
+            Some(ref url) => CString::new(url.as_str())?,
+            None => CString::new(content_url(&content, self.csp.as_deref())?)?,
         };
-        let data = Box::new(
+        let mut protocols = self.protocols;
+
+        let mut cors_protocols = self.cors_protocols;
+        let mut content_scheme = None;
+
+        if let (Some((scheme, handler)), None) = (content.protocol()?, served) {
+            if !protocols.contains_key(scheme) {
+                protocols.insert(scheme.to_string(), handler);
+                content_scheme = Some(scheme);
+            }
+
+            cors_protocols.push(scheme.to_string());
+        }
+
+        // Schemes must be registered before the WebView using them is created
+        for scheme in protocols.keys() {
+            let cors = cors_protocols.contains(scheme);
+            let scheme = CString::new(scheme.as_str())?;
+
+            if unsafe { webview_register_uri_scheme(scheme.as_ptr(), cors as _) } != 0 {
+                return Err(Error::Unsupported("custom URI schemes"));
+            }
+        }
+
+        let mut data = Box::new(
             WebViewStateData::new(
                 user_data,
-                Box::new(invoke_handler),
+                invoke_handler,
             )
         );
 
-        WebView::new(
+        data.forward_errors = self.forward_errors;
+        data.allowlist = self.allowlist;
+        data.max_message_size = self.max_message_size;
+        data.dispatch_queue = Arc::new(DispatchQueue::new(self.dispatch_queue));
+        data.content_url = url.to_string_lossy().into_owned();
+        data.page_url = data.content_url.clone();
+        data.frame_key = secret::random()?;
+        data.csp = self.csp;
+        let has_protocols = !protocols.is_empty();
+        data.protocols = protocols;
+        data.content_scheme = content_scheme;
+        let has_load_handler = self.load_handler.is_some();
+        data.load_handler = self.load_handler;
+        data.dom_content_loaded_handler = self.dom_content_loaded_handler;
+        let has_navigation_handler = self.navigation_handler.is_some();
+        data.navigation_handler = self.navigation_handler;
+        #[cfg(feature = "server")]
+        {
+            data.server = server;
+        }
+
+        let mut webview = WebView::new(
             WebViewFFI::new(
                 url.as_ptr(),
                 title.as_ptr(),
@@ -231,10 +1105,85 @@ where
                 ffi_invoke_handler::<T>,
                 Box::into_raw(data) as _,
             )
-        )
+        )?;
+
+        // Applied before the main loop runs, so before the first page is requested
+        if self.settings != WebSettings::default() {
+            self.settings.apply(webview.internal)?;
+        }
+
+        if has_protocols {
+            unsafe { webview_set_uri_scheme_handler(webview.internal, Some(ffi_uri_scheme_handler::<T>)) };
+        }
+
+        if has_navigation_handler
+            && unsafe { webview_set_navigation_handler(webview.internal, Some(ffi_navigation_handler::<T>)) } != 0
+        {
+            return Err(Error::Unsupported("navigation handlers"));
+        }
+
+        let native_load_events = unsafe { webview_set_load_handler(webview.internal, Some(ffi_load_handler::<T>)) } == 0;
+        webview.state_data_mut().native_load_events = native_load_events;
+
+        if has_load_handler && !native_load_events {
+            return Err(Error::Unsupported("load events"));
+        }
+
+        if let Some((root, recursive)) = watched {
+            let live = Arc::downgrade(&webview.state_data().live_lock());
+
+            webview.state_data_mut().watcher = Some(Watcher::start(root, recursive, webview.internal, live));
+        }
+
+        // Apps only using the invoke handler and `eval` still work without it
+        match webview.add_user_script(&bridge::source(&webview.state_data().frame_key, webview.state_data().max_message_size)) {
+            Ok(()) => webview.state_data_mut().bridge = true,
+            Err(Error::Unsupported(_)) if !needs_bridge => {},
+            Err(e) => return Err(e),
+        }
+
+        for js in &self.init_scripts {
+            webview.add_init_script(js)?;
+        }
+
+        Ok(webview)
     }
 }
 
+/// Turns the return value `ret` of a navigation into an error where the platform cannot navigate.
+fn navigation(ret: c_int) -> WVResult {
+    match ret {
+        0 => Ok(()),
+        _ => Err(Error::Unsupported("navigation")),
+    }
+}
+
+/// Returns the URL `content` is loaded from, applying the Content Security Policy `csp` to HTML.
+fn content_url<C: AsRef<str>>(content: &Content<C>, csp: Option<&str>) -> WVResult<String> {
+    match (content, csp) {
+        (Content::Html(html), Some(csp)) => Content::Html(csp::insert_meta(html.as_ref(), csp)).to_url(),
+        // Pages loaded from `file://` URLs cannot be given a policy
+        (Content::File(_), Some(_)) => {
+            Err(Error::Unsupported("content security policies for local files loaded directly"))
+        },
+        _ => content.to_url(),
+    }
+}
+
+/// Combines registered commands and the invoke handler, if any, into a single invoke handler.
+fn route<'a, T: 'a>(mut commands: Router<'a, T>, mut fallback: Option<InvokeHandler<'a, T>>) -> InvokeHandler<'a, T> {
+    Box::new(move |webview, arg| {
+        let request = Request::parse(arg);
+
+        match (request, &mut fallback) {
+            (Ok(ref request), _) if commands.contains(request.name()) => commands.call(webview, request),
+            (_, Some(fallback)) => fallback(webview, arg),
+            (Ok(ref request), None) => commands.call(webview, request),
+            (Err(e), None) => Err(e),
+        }
+    })
+}
+
 impl<T> WebView<T> {
     pub fn new(internal: WebViewFFI) -> WVResult<Self> {
         unsafe {
@@ -276,6 +1225,13 @@ impl<T> WebView<T> {
         }
     }
 
+    fn state_data(&self) -> &WebViewStateData<'_, T> {
+        unsafe {
+            let ffi: &WebViewFFI = &*self.internal;
+            &*(ffi.userdata as *mut WebViewStateData<T>)
+        }
+    }
+
     fn state_data_mut(&mut self) -> &mut WebViewStateData<T> {
         unsafe {
             let ffi: &WebViewFFI = &*self.internal;
@@ -327,6 +1283,333 @@ impl<T> WebView<T> {
         }
     }
 
+    /// Emits `event` to the listeners added in JavaScript with `rust.on(event, listener)`, passing
+    /// them `payload`.
+    ///
+    /// Returns [`Error::Unsupported`] where the bridge script cannot run, see
+    /// [`WebViewBuilder::build`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use webview::*;
+    /// #
+    /// # fn render(webview: &mut WebView<()>, tasks: Vec<String>) -> WVResult {
+    /// // In JavaScript: rust.on('render', function(tasks) { ... });
+    /// webview.emit("render", &tasks)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Json`] if `payload` cannot be serialized.
+    ///
+    /// [`Error::Unsupported`]: enum.Error.html#variant.Unsupported
+    /// [`WebViewBuilder::build`]: struct.WebViewBuilder.html#method.build
+    /// [`Error::Json`]: enum.Error.html#variant.Json
+    pub fn emit<P: Serialize>(&mut self, event: &str, payload: P) -> WVResult {
+        let payload = serde_json::to_value(payload)?;
+
+        bridge::send(self, &Outgoing::Emit { event, payload: &payload })
+    }
+
+    /// Adds a script which runs at the start of every document loaded in the `WebView` instance.
+    ///
+    /// Returns [`Error::Unsupported`] where the platform cannot run scripts on every page load.
+    ///
+    /// [`Error::Unsupported`]: enum.Error.html#variant.Unsupported
+    fn add_user_script(&mut self, js: &str) -> WVResult {
+        let js = CString::new(js)?;
+        let ret = unsafe {
+            webview_add_user_script(self.internal, js.as_ptr())
+        };
+
+        match ret {
+            0 => Ok(()),
+            _ => Err(Error::Unsupported("scripts run on every page load")),
+        }
+    }
+
+    /// Adds `js` to the scripts run at the start of every page loaded from now on, before the
+    /// scripts of the page. See [`WebViewBuilder::init_script`].
+    ///
+    /// Returns the id to remove the script with.
+    ///
+    /// [`WebViewBuilder::init_script`]: struct.WebViewBuilder.html#method.init_script
+    pub fn add_init_script(&mut self, js: &str) -> WVResult<ScriptId> {
+        self.add_user_script(js)?;
+
+        let data = self.state_data_mut();
+        let id = data.next_script_id;
+
+        data.next_script_id += 1;
+        data.init_scripts.push((id, js.to_string()));
+        Ok(ScriptId(id))
+    }
+
+    /// Removes the script added with [`add_init_script`] or [`WebViewBuilder::init_script`] from
+    /// the pages loaded from now on. Removing a script twice does nothing.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Unsupported`] where the platform does not support init scripts.
+    ///
+    /// [`add_init_script`]: struct.WebView.html#method.add_init_script
+    /// [`WebViewBuilder::init_script`]: struct.WebViewBuilder.html#method.init_script
+    /// [`Error::Unsupported`]: enum.Error.html#variant.Unsupported
+    pub fn remove_init_script(&mut self, id: ScriptId) -> WVResult {
+        let scripts = &mut self.state_data_mut().init_scripts;
+        let count = scripts.len();
+
+        scripts.retain(|(i, _)| *i != id.0);
+
+        if scripts.len() == count {
+            return Ok(());
+        }
+
+        let scripts = scripts.clone();
+
+        // Scripts can only be removed together, so add back the others
+        if unsafe { webview_remove_user_scripts(self.internal) } != 0 {
+            return Err(Error::Unsupported("removing init scripts"));
+        }
+
+        self.add_user_script(&bridge::source(&self.state_data().frame_key, self.state_data().max_message_size))?;
+
+        for (_, js) in scripts {
+            self.add_user_script(&js)?;
+        }
+
+        Ok(())
+    }
+
+    /// Evaluates the JavaScript expression `js` within the `WebView` instance and passes its result,
+    /// deserialized into `R`, to `callback`.
+    ///
+    /// If the expression evaluates to a promise, `callback` receives the value it settles with.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use webview::*;
+    /// #
+    /// # fn measure(webview: &mut WebView<()>) -> WVResult {
+    /// webview.eval_with_result("window.innerWidth", |_webview, width: WVResult<i32>| {
+    ///     println!("width: {}", width?);
+    ///     Ok(())
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::JsEvaluation`] if `js` could not be evaluated, or [`Error::Unsupported`]
+    /// where the bridge script cannot run, see [`WebViewBuilder::build`].
+    ///
+    /// `callback` receives [`Error::JsException`] if the expression throws or its promise is
+    /// rejected, [`Error::Json`] if the result cannot be deserialized into `R`, and
+    /// [`Error::Unloaded`] if another page is loaded first. If `callback` returns an `Err`, it will
+    /// be returned on the next call to [`step()`].
+    ///
+    /// [`Error::JsEvaluation`]: enum.Error.html#variant.JsEvaluation
+    /// [`Error::Unsupported`]: enum.Error.html#variant.Unsupported
+    /// [`WebViewBuilder::build`]: struct.WebViewBuilder.html#method.build
+    /// [`Error::Unloaded`]: enum.Error.html#variant.Unloaded
+    /// [`Error::JsException`]: enum.Error.html#variant.JsException
+    /// [`Error::Json`]: enum.Error.html#variant.Json
+    /// [`step()`]: struct.WebView.html#method.step
+    pub fn eval_with_result<R, F>(&mut self, js: &str, callback: F) -> WVResult
+    where
+        R: DeserializeOwned,
+        F: FnOnce(&mut WebView<T>, WVResult<R>) -> WVResult + 'static,
+    {
+        if !self.state_data().bridge {
+            return Err(Error::Unsupported("the JavaScript bridge"));
+        }
+
+        let data = self.state_data_mut();
+        let id = data.next_eval_id;
+
+        data.next_eval_id += 1;
+        data.pending_evals.insert(id, Box::new(move |webview, result| {
+            callback(webview, result.and_then(|value| Ok(serde_json::from_value(value)?)))
+        }));
+
+        // The newline keeps a trailing line comment in `js` from swallowing the closing brace
+        let js = format!("window.rust._evaluate({}, function() {{ return ({}\n); }})", id, js);
+
+        let result = self.eval(&js);
+
+        if result.is_err() {
+            self.state_data_mut().pending_evals.remove(&id);
+        }
+
+        result
+    }
+
+    /// Returns `true` if `error`, returned by a handler, is to be forwarded to JavaScript rather than
+    /// returned from [`step()`].
+    ///
+    /// [`step()`]: struct.WebView.html#method.step
+    fn forwards_error(&mut self, error: &Error) -> bool {
+        let data = self.state_data_mut();
+
+        data.forward_errors && data.bridge && !error.is_fatal()
+    }
+
+    /// Emits a handler error to JavaScript as an `error` event if errors are forwarded, otherwise
+    /// returns it.
+    fn forward_error(&mut self, result: WVResult) -> WVResult {
+        match result {
+            Err(ref e) if self.forwards_error(e) => bridge::send(self, &Outgoing::Error { error: e.into() }),
+            result => result,
+        }
+    }
+
+    /// Passes the result of an expression evaluated by [`eval_with_result()`] to its callback.
+    ///
+    /// [`eval_with_result()`]: struct.WebView.html#method.eval_with_result
+    fn evaluated(&mut self, id: u64, result: WVResult<Value>) -> WVResult {
+        let callback = self.state_data_mut().pending_evals.remove(&id);
+
+        match callback {
+            Some(callback) => callback(self, result),
+            None => Ok(()),
+        }
+    }
+
+    /// Abandons what was left pending by the page being replaced by another.
+    fn unloaded(&mut self) -> WVResult {
+        let data = self.state_data_mut();
+
+        // Ids of calls and chunked messages restart with every page
+        data.page += 1;
+        data.chunks = None;
+        data.dom_content_loaded = false;
+
+        for (_, token) in data.cancellations.drain() {
+            token.cancel();
+        }
+
+        let pending: Vec<_> = self.state_data_mut().pending_evals.drain().collect();
+        let mut result = Ok(());
+
+        for (_, callback) in pending {
+            let unloaded = callback(self, Err(Error::Unloaded));
+
+            if result.is_ok() {
+                result = unloaded;
+            }
+        }
+
+        result
+    }
+
+    /// Returns the URL of the current page, or of the content last loaded where the platform does
+    /// not report it.
+    pub fn current_url(&self) -> String {
+        let uri = unsafe { webview_get_uri(self.internal) };
+
+        if uri.is_null() {
+            self.state_data().content_url.clone()
+        } else {
+            unsafe { CStr::from_ptr(uri) }.to_string_lossy().into_owned()
+        }
+    }
+
+    /// Replaces the current page with `content`.
+    ///
+    /// [`Content::Dir`] and [`Content::Assets`] can only be loaded if the WebView was built with
+    /// content served through the same URI scheme, which the new content then replaces, and not if
+    /// a handler was set for that scheme with [`WebViewBuilder::protocol`]. Content is never served
+    /// by the server started with [`WebViewBuilder::serve`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use webview::*;
+    /// #
+    /// # fn login(handle: Handle<()>) -> WVResult {
+    /// handle.dispatch(|webview| webview.navigate(Content::Url("https://app.example.com/main")))
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if the path of `Content::File` or `Content::Dir` cannot be resolved,
+    /// or [`Error::Unsupported`] for content which cannot be served as described above, and
+    /// anywhere but WebKitGTK, like the other navigation methods.
+    ///
+    /// [`Content::Dir`]: enum.Content.html#variant.Dir
+    /// [`Content::Assets`]: enum.Content.html#variant.Assets
+    /// [`WebViewBuilder::protocol`]: struct.WebViewBuilder.html#method.protocol
+    /// [`WebViewBuilder::serve`]: struct.WebViewBuilder.html#method.serve
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    /// [`Error::Unsupported`]: enum.Error.html#variant.Unsupported
+    pub fn navigate<C: AsRef<str>>(&mut self, content: Content<C>) -> WVResult {
+        let url = content_url(&content, self.state_data().csp.as_deref())?;
+
+        // URI schemes cannot be registered once the WebView has been created, and handlers set
+        // with `protocol()` are kept
+        if let Some((scheme, handler)) = content.protocol()? {
+            let data = self.state_data_mut();
+
+            if !data.protocols.contains_key(scheme) {
+                return Err(Error::Unsupported("loading content through a new URI scheme"));
+            }
+
+            if data.content_scheme != Some(scheme) {
+                return Err(Error::Unsupported("replacing a URI scheme handler set with protocol()"));
+            }
+
+            data.protocols.insert(scheme.to_string(), handler);
+        }
+
+        let c_url = CString::new(url.as_str())?;
+        let ret = unsafe { webview_navigate(self.internal, c_url.as_ptr()) };
+
+        navigation(ret)?;
+        self.state_data_mut().content_url = url;
+        Ok(())
+    }
+
+    /// Reloads the current page.
+    pub fn reload(&mut self) -> WVResult {
+        let ret = unsafe { webview_reload(self.internal) };
+
+        navigation(ret)
+    }
+
+    /// Goes back to the previous page in history, if any.
+    pub fn go_back(&mut self) -> WVResult {
+        let ret = unsafe { webview_go_back(self.internal) };
+
+        navigation(ret)
+    }
+
+    /// Goes forward to the next page in history, if any.
+    pub fn go_forward(&mut self) -> WVResult {
+        let ret = unsafe { webview_go_forward(self.internal) };
+
+        navigation(ret)
+    }
+
+    /// Stops loading the current page.
+    pub fn stop_loading(&mut self) -> WVResult {
+        let ret = unsafe { webview_stop_loading(self.internal) };
+
+        navigation(ret)
+    }
+
+    /// Cancels the call `id`, abandoned by JavaScript.
+    fn cancel(&mut self, id: u64) {
+        if let Some(token) = self.state_data_mut().cancellations.remove(&id) {
+            token.cancel();
+        }
+    }
+
     /// Injects the provided string as CSS within the `WebView` instance.
     pub fn inject_css(&mut self, css: &str) -> WVResult {
         let css = CString::new(css)?;
@@ -413,6 +1696,8 @@ impl<T> WebView<T> {
             Handle::new(
                 self.internal,
                 user_data.live_lock(),
+                user_data.dispatch_queue.clone(),
+                user_data.batch.clone(),
             )
         }
     }
@@ -423,6 +1708,7 @@ impl<T> Drop for WebView<T> {
         unsafe {
             let ffi: &WebViewFFI = &*self.internal;
 
+            (*(ffi.userdata as *mut WebViewStateData<T>)).dispatch_queue.close();
             webview_exit(self.internal);
 
             // Drop both WebViewStateData and WebViewFFI which were
@@ -433,29 +1719,210 @@ impl<T> Drop for WebView<T> {
     }
 }
 
-pub unsafe extern "C" fn ffi_dispatch_handler<F, T>(ffi: *mut WebViewFFI, arg: *mut c_void)
-where
-    F: FnOnce(&mut WebView<T>) -> WVResult + Send + 'static,
-{
+pub unsafe extern "C" fn ffi_dispatch_handler<T>(ffi: *mut WebViewFFI, _arg: *mut c_void) {
     let mut webview = ManuallyDrop::new(WebView::<T>::from_ptr(ffi));
     let webffi: &WebViewFFI = &*ffi;
     let data: &mut WebViewStateData<T> = &mut *(webffi.userdata as *mut WebViewStateData<T>);
+    let queue = data.dispatch_queue.clone();
+
+    // Run the closures queued so far, leaving later ones and those after an error to another
+    // iteration of the event loop
+    for _ in 0..queue.len() {
+        let closure = match queue.pop() {
+            Some(closure) => closure,
+            None => break,
+        };
+        let result = closure(&mut webview);
+        let failed = result.is_err();
+
+        data.set_result(result);
 
-    data.result = Some({
-        let closure: Box<F> = Box::from_raw(arg as _);
-        (*closure)(&mut webview)
-    });
+        if failed {
+            break;
+        }
+    }
+
+    if queue.reschedule() {
+        webview_dispatch(ffi, Some(ffi_dispatch_handler::<T>), ptr::null_mut());
+    }
+}
+
+unsafe extern "C" fn ffi_uri_scheme_handler<T>(ffi: *mut WebViewFFI, request: *mut c_void, uri: *const c_char) {
+    let webffi: &WebViewFFI = &*ffi;
+    let data: &mut WebViewStateData<T> = &mut *(webffi.userdata as *mut WebViewStateData<T>);
+    let uri = CStr::from_ptr(uri).to_string_lossy();
+    let scheme = uri.split(':').next().unwrap_or_default();
+
+    let handler = match data.protocols.get_mut(scheme) {
+        Some(handler) => handler,
+        None => {
+            let message = CString::new(format!("No handler for {}", uri)).unwrap_or_default();
+
+            webview_uri_scheme_fail(request, message.as_ptr());
+            return;
+        },
+    };
+    let protocol_request = ProtocolRequest::from_ffi(request, &uri);
+    let response = handler(&protocol_request).finish(&protocol_request);
+    let response = match &data.csp {
+        Some(csp) => response.with_csp(csp),
+        None => response,
+    };
+    let headers: Vec<(CString, CString)> = response.headers
+        .iter()
+        .filter_map(|(name, value)| Some((CString::new(name.as_str()).ok()?, CString::new(value.as_str()).ok()?)))
+        .collect();
+    let names: Vec<*const c_char> = headers.iter().map(|(name, _)| name.as_ptr()).collect();
+    let values: Vec<*const c_char> = headers.iter().map(|(_, value)| value.as_ptr()).collect();
+    let mime = CString::new(response.mime.unwrap_or_default()).unwrap_or_default();
+
+    webview_uri_scheme_respond(
+        request,
+        c_int::from(response.status),
+        names.as_ptr(),
+        values.as_ptr(),
+        headers.len(),
+        response.body.as_ptr() as _,
+        response.body.len(),
+        mime.as_ptr(),
+    );
+}
+
+unsafe extern "C" fn ffi_navigation_handler<T>(ffi: *mut WebViewFFI, uri: *const c_char, kind: c_int) -> c_int {
+    let webffi: &WebViewFFI = &*ffi;
+    let data: &mut WebViewStateData<T> = &mut *(webffi.userdata as *mut WebViewStateData<T>);
+    let uri = CStr::from_ptr(uri).to_string_lossy();
+
+    match data.navigation_handler.as_mut() {
+        Some(handler) => handler(&uri, NavigationKind::from_ffi(kind)).to_ffi(),
+        None => Decision::Allow.to_ffi(),
+    }
+}
+
+unsafe extern "C" fn ffi_load_handler<T>(ffi: *mut WebViewFFI, event: c_int, uri: *const c_char, error: *const c_char) {
+    let webffi: &WebViewFFI = &*ffi;
+    let mut webview = ManuallyDrop::new(WebView::<T>::from_ptr(ffi));
+    let data: &mut WebViewStateData<T> = &mut *(webffi.userdata as *mut WebViewStateData<T>);
+    let string = |ptr: *const c_char| match ptr.is_null() {
+        true => None,
+        false => Some(CStr::from_ptr(ptr).to_string_lossy().into_owned()),
+    };
+    let event = match LoadEvent::from_ffi(event, string(uri).unwrap_or_default(), string(error)) {
+        Some(event) => event,
+        None => return,
+    };
+
+    // Only once committed is the previous page gone, as a load failing before keeps it
+    if let LoadEvent::Committed { ref url } = event {
+        data.page_url = url.clone();
+        let result = webview.unloaded();
+        data.set_result(webview.forward_error(result));
+    }
+
+    if let Some(handler) = data.load_handler.as_mut() {
+        let result = handler(&mut webview, &event);
+        data.set_result(webview.forward_error(result));
+    }
 }
 
 pub unsafe extern "C" fn ffi_invoke_handler<T>(ffi: *mut WebViewFFI, arg: *const c_char) {
     let webffi: &WebViewFFI = &*ffi;
-    let arg = CStr::from_ptr(arg).to_string_lossy().to_string();
     let mut webview = ManuallyDrop::new(WebView::<T>::from_ptr(ffi));
     let data: &mut WebViewStateData<T> = &mut *(webffi.userdata as *mut WebViewStateData<T>);
+    let arg = CStr::from_ptr(arg).to_bytes();
+
+    // Frames within the page can post messages too, but only the bridge script of the top frame
+    // knows the key
+    let arg = match (data.bridge, arg.strip_prefix(data.frame_key.as_bytes())) {
+        (false, _) => arg,
+        (true, Some(arg)) => arg,
+        (true, None) => return,
+    };
+    let limit = data.max_message_size;
+
+    match limit {
+        Some(limit) if arg.len() > limit => {
+            let error = Error::MessageTooLarge { size: arg.len(), limit };
+            data.set_result(webview.forward_error(Err(error)));
+            return;
+        },
+        _ => {},
+    }
+
+    let arg = String::from_utf8_lossy(arg).into_owned();
+
+    let parsed = match data.bridge {
+        true => Incoming::parse(&arg),
+        false => None,
+    };
+
+    // Large messages arrive in chunks, handled once the last has been received
+    let (arg, incoming) = match parsed {
+        Some(Ok(Incoming::Chunk { id, data: chunk, last })) => {
+            match bridge::reassemble(&mut data.chunks, id, &chunk, last, limit) {
+                Ok(Some(message)) => {
+                    let incoming = Incoming::parse(&message);
+                    (message, incoming)
+                },
+                Ok(None) => return,
+                Err(e) => {
+                    data.set_result(webview.forward_error(Err(e)));
+                    return;
+                },
+            }
+        },
+        incoming => (arg, incoming),
+    };
+
+    // The URL of the page which sent the message, which `current_url()` no longer is once another
+    // page is being loaded
+    let url = match data.native_load_events {
+        true => data.page_url.clone(),
+        false => webview.current_url(),
+    };
+
+    if !data.allowlist.is_empty() {
+        let access = match incoming {
+            Some(Ok(Incoming::Call { ref name, .. })) => Access::Call(name),
+            Some(Ok(_)) => Access::Bridge,
+            _ => Access::Raw,
+        };
+
+        if !data.allowlist.check(Some(&url), access, &arg) {
+            if let Some(Ok(Incoming::Call { id, name, .. })) = incoming {
+                // Settle the promise, which would otherwise never be
+                let error = Error::Forbidden(name);
+                let _ = bridge::send(&mut *webview, &Outgoing::Reject { id, error: (&error).into() });
+            }
+
+            return;
+        }
+    }
+
+    let result = match incoming {
+        Some(Ok(Incoming::Evaluated { id, value, error })) => {
+            webview.evaluated(id, error.map_or(Ok(value), |e| Err(e.into())))
+        },
+        Some(Ok(Incoming::Cancel { id })) => {
+            webview.cancel(id);
+            Ok(())
+        },
+        // Pages can post these too, so only the first of each page is passed on, with its URL as
+        // known to Rust
+        Some(Ok(Incoming::DomContentLoaded)) => match data.dom_content_loaded_handler.as_mut() {
+            Some(handler) if !std::mem::replace(&mut data.dom_content_loaded, true) => {
+                let result = handler(&mut webview, &url);
+                webview.forward_error(result)
+            },
+            _ => Ok(()),
+        },
+        _ => {
+            let result = (data.invoke_handler)(&mut webview, &arg);
+            webview.forward_error(result)
+        },
+    };
 
-    data.result = Some({
-        (data.invoke_handler)(&mut webview, &arg)
-    });
+    data.set_result(result);
 }
 
 #[cfg(test)]
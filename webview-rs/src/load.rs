@@ -0,0 +1,38 @@
+/// A step in loading a page, passed to the handler set with [`WebViewBuilder::on_load`].
+///
+/// [`WebViewBuilder::on_load`]: struct.WebViewBuilder.html#method.on_load
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadEvent {
+    /// Loading `url` has started.
+    Started { url: String },
+    /// The server has responded and the page at `url` replaces the previous one.
+    Committed { url: String },
+    /// The page at `url` has loaded, including its subresources, or has failed to.
+    Finished { url: String },
+    /// Loading `url` has failed with `error`. Followed by `Finished`.
+    Failed { url: String, error: String },
+}
+
+impl LoadEvent {
+    /// Returns the URL of the page.
+    pub fn url(&self) -> &str {
+        match self {
+            LoadEvent::Started { url }
+            | LoadEvent::Committed { url }
+            | LoadEvent::Finished { url }
+            | LoadEvent::Failed { url, .. } => url,
+        }
+    }
+
+    /// Creates the event reported by the platform as `event`, or `None` for events which are not
+    /// passed on.
+    pub(crate) fn from_ffi(event: i32, url: String, error: Option<String>) -> Option<Self> {
+        match event {
+            0 => Some(LoadEvent::Started { url }),
+            2 => Some(LoadEvent::Committed { url }),
+            3 => Some(LoadEvent::Finished { url }),
+            4 => Some(LoadEvent::Failed { url, error: error.unwrap_or_default() }),
+            _ => None,
+        }
+    }
+}
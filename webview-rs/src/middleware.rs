@@ -0,0 +1,88 @@
+use crate::error::{
+    Error,
+    WVResult,
+};
+use crate::rpc::Request;
+use crate::state_data::InvokeHandler;
+use crate::WebView;
+use std::any::Any;
+use std::panic::{
+    self,
+    AssertUnwindSafe,
+};
+
+pub type Layer<'a, T> = Box<dyn FnMut(&mut WebView<T>, &str, Next<T>) -> WVResult + 'a>;
+
+/// The remainder of the invoke chain, passed to each middleware layer.
+///
+/// See [`WebViewBuilder::middleware`].
+///
+/// [`WebViewBuilder::middleware`]: struct.WebViewBuilder.html#method.middleware
+pub struct Next<'b, T> {
+    handler: &'b mut dyn FnMut(&mut WebView<T>, &str) -> WVResult,
+}
+
+impl<'b, T> Next<'b, T> {
+    /// Passes `message` on to the next layer, or to the invoke handler and commands after the last
+    /// layer.
+    pub fn run(self, webview: &mut WebView<T>, message: &str) -> WVResult {
+        (self.handler)(webview, message)
+    }
+}
+
+/// Wraps `handler` in `layers`, the first of which is outermost.
+pub fn wrap<'a, T: 'a>(mut handler: InvokeHandler<'a, T>, layers: Vec<Layer<'a, T>>) -> InvokeHandler<'a, T> {
+    for mut layer in layers.into_iter().rev() {
+        let mut next = handler;
+
+        handler = Box::new(move |webview, message| {
+            layer(webview, message, Next { handler: &mut *next })
+        });
+    }
+
+    handler
+}
+
+/// Middleware which turns a panic in later layers, commands or the invoke handler into
+/// [`Error::Panic`], rejecting the promise returned by `rust.call` if the message was a call.
+///
+/// A panic must not unwind into webview, so this is best registered as the outermost layer.
+///
+/// [`Error::Panic`]: enum.Error.html#variant.Panic
+pub fn catch_panic<T>() -> impl FnMut(&mut WebView<T>, &str, Next<T>) -> WVResult {
+    |webview: &mut WebView<T>, message: &str, next: Next<T>| {
+        let payload = match panic::catch_unwind(AssertUnwindSafe(|| next.run(webview, message))) {
+            Ok(result) => return result,
+            Err(payload) => payload,
+        };
+        let error = Error::Panic(panic_message(payload));
+
+        // Settle the promise, which would otherwise never be
+        match Request::parse(message) {
+            Ok(request) => request.respond(webview, Err(error)),
+            Err(_) => Err(error),
+        }
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&'static str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "Box<Any>".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_panic_message() {
+        assert_eq!("static", panic_message(Box::new("static")));
+        assert_eq!("owned", panic_message(Box::new("owned".to_string())));
+        assert_eq!("Box<Any>", panic_message(Box::new(1)));
+    }
+}
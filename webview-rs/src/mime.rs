@@ -0,0 +1,50 @@
+/// Returns the MIME type of a file from the extension of its `path`, defaulting to
+/// `application/octet-stream`.
+pub fn guess(path: &str) -> &'static str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let extension = match name.rfind('.') {
+        Some(i) => name[i + 1..].to_ascii_lowercase(),
+        None => return "application/octet-stream",
+    };
+
+    match extension.as_str() {
+        "html" | "htm" => "text/html",
+        "js" | "mjs" => "text/javascript",
+        "css" => "text/css",
+        "json" | "map" => "application/json",
+        "wasm" => "application/wasm",
+        "txt" => "text/plain",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_guess() {
+        assert_eq!("text/html", guess("index.html"));
+        assert_eq!("text/javascript", guess("js/app.min.JS"));
+        assert_eq!("font/woff2", guess("fonts/a.b/icons.woff2"));
+        assert_eq!("application/octet-stream", guess("a.b/LICENSE"));
+        assert_eq!("application/octet-stream", guess(""));
+    }
+}
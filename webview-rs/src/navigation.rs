@@ -0,0 +1,77 @@
+/// What started a navigation, passed to the handler set with [`WebViewBuilder::on_navigation`].
+///
+/// [`WebViewBuilder::on_navigation`]: struct.WebViewBuilder.html#method.on_navigation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NavigationKind {
+    /// A link was clicked.
+    LinkClicked,
+    /// A form was submitted.
+    FormSubmitted,
+    /// The user went back or forward in history.
+    BackForward,
+    /// The page is reloaded.
+    Reload,
+    /// A form is submitted again, e.g. when reloading its result.
+    FormResubmitted,
+    /// The page asks for a new window, e.g. with a link targeting `_blank` or `window.open`.
+    /// Allowing it loads the page in the WebView itself.
+    NewWindow,
+    /// Anything else, such as scripts assigning `location` or calls to
+    /// [`WebView::navigate`].
+    ///
+    /// [`WebView::navigate`]: struct.WebView.html#method.navigate
+    Other,
+}
+
+impl NavigationKind {
+    pub(crate) fn from_ffi(kind: i32) -> Self {
+        match kind {
+            0 => NavigationKind::LinkClicked,
+            1 => NavigationKind::FormSubmitted,
+            2 => NavigationKind::BackForward,
+            3 => NavigationKind::Reload,
+            4 => NavigationKind::FormResubmitted,
+            6 => NavigationKind::NewWindow,
+            _ => NavigationKind::Other,
+        }
+    }
+}
+
+/// Whether a navigation goes ahead, returned by the handler set with
+/// [`WebViewBuilder::on_navigation`].
+///
+/// [`WebViewBuilder::on_navigation`]: struct.WebViewBuilder.html#method.on_navigation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decision {
+    /// Let the WebView load the page.
+    Allow,
+    /// Stay on the current page.
+    Block,
+    /// Stay on the current page, and open the URL in the default browser instead. Only `http:`,
+    /// `https:` and `mailto:` URLs are opened, others are blocked.
+    OpenInBrowser,
+}
+
+impl Decision {
+    pub(crate) fn to_ffi(self) -> i32 {
+        match self {
+            Decision::Allow => 0,
+            Decision::Block => 1,
+            Decision::OpenInBrowser => 2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_ffi() {
+        assert_eq!(NavigationKind::LinkClicked, NavigationKind::from_ffi(0));
+        assert_eq!(NavigationKind::Reload, NavigationKind::from_ffi(3));
+        assert_eq!(NavigationKind::Other, NavigationKind::from_ffi(5));
+        assert_eq!(NavigationKind::NewWindow, NavigationKind::from_ffi(6));
+        assert_eq!(2, Decision::OpenInBrowser.to_ffi());
+    }
+}
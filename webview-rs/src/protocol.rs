@@ -0,0 +1,350 @@
+use crate::csp;
+use crate::mime;
+use std::borrow::Cow;
+use std::ffi::CStr;
+use std::os::raw::{
+    c_char,
+    c_void,
+};
+use std::slice;
+use urlencoding::decode;
+use webview_sys::{
+    webview_uri_scheme_body,
+    webview_uri_scheme_headers,
+    webview_uri_scheme_method,
+};
+
+pub type ProtocolHandler<'a> = Box<dyn FnMut(&ProtocolRequest) -> Response + 'a>;
+
+/// A request made by the page to a custom URI scheme, passed to handlers registered with
+/// [`WebViewBuilder::protocol`].
+///
+/// The method and headers are only known with WebKitGTK 2.36 and later, and the body with 2.40
+/// and later. Before that, every request is a `GET` without headers.
+///
+/// [`WebViewBuilder::protocol`]: struct.WebViewBuilder.html#method.protocol
+#[derive(Debug)]
+pub struct ProtocolRequest {
+    method: String,
+    uri: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl ProtocolRequest {
+    /// Creates a request without headers or body, e.g. to test a handler.
+    pub fn new(method: &str, uri: &str) -> Self {
+        ProtocolRequest {
+            method: method.to_string(),
+            uri: uri.to_string(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Adds a header.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sets the body.
+    #[cfg(feature = "server")]
+    pub(crate) fn with_body(mut self, body: Vec<u8>) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// Reads the request made with `uri` from WebKit.
+    pub(crate) unsafe fn from_ffi(request: *mut c_void, uri: &str) -> Self {
+        let mut headers = Vec::new();
+        let mut body = Vec::new();
+
+        webview_uri_scheme_headers(request, Some(push_header), &mut headers as *mut _ as _);
+        webview_uri_scheme_body(request, Some(push_body), &mut body as *mut _ as _);
+
+        ProtocolRequest {
+            method: CStr::from_ptr(webview_uri_scheme_method(request)).to_string_lossy().into_owned(),
+            uri: uri.to_string(),
+            headers,
+            body,
+        }
+    }
+
+    /// Returns the HTTP method, e.g. `"GET"`.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Returns the full URI, e.g. `"api://localhost/users?page=2"`.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Returns the decoded path of the URI, without the query or fragment, e.g. `"/users"`.
+    pub fn path(&self) -> String {
+        path(&self.uri)
+    }
+
+    /// Returns the value of the header `name`, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the headers as pairs of names and values.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// Returns the body, empty for `GET` requests.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+}
+
+unsafe extern "C" fn push_header(name: *const c_char, value: *const c_char, headers: *mut c_void) {
+    let headers = &mut *(headers as *mut Vec<(String, String)>);
+
+    headers.push((
+        CStr::from_ptr(name).to_string_lossy().into_owned(),
+        CStr::from_ptr(value).to_string_lossy().into_owned(),
+    ));
+}
+
+unsafe extern "C" fn push_body(data: *const c_char, len: usize, body: *mut c_void) {
+    let body = &mut *(body as *mut Vec<u8>);
+
+    body.extend_from_slice(slice::from_raw_parts(data as *const u8, len));
+}
+
+/// Returns the decoded path of `uri`, without the query or fragment.
+pub(crate) fn path(uri: &str) -> String {
+    let rest = match uri.find("://") {
+        Some(i) => &uri[i + 3..],
+        None => uri,
+    };
+    // Skip the host
+    let path = rest.find('/').map(|i| &rest[i..]).unwrap_or("/");
+    let path = path.split(&['?', '#'][..]).next().unwrap_or(path);
+
+    decode(path).unwrap_or_else(|_| path.to_string())
+}
+
+/// The response to a [`ProtocolRequest`].
+///
+/// Responses with status 200 to requests with a `Range` header only hold the range requested,
+/// with status 206, so that `<video>` and `<audio>` elements can seek through media served from
+/// Rust.
+///
+/// # Example
+///
+/// ```
+/// # use webview::*;
+/// let response = Response::new(b"{}".to_vec())
+///     .mime("application/json")
+///     .header("Cache-Control", "no-store");
+///
+/// assert_eq!(200, response.status);
+/// assert_eq!(404, Response::not_found().status);
+/// ```
+///
+/// [`ProtocolRequest`]: struct.ProtocolRequest.html
+#[derive(Debug)]
+pub struct Response {
+    /// The HTTP status code, 200 by default.
+    pub status: u16,
+    /// The headers as pairs of names and values.
+    pub headers: Vec<(String, String)>,
+    /// The MIME type, guessed from the extension of the path requested if `None`.
+    pub mime: Option<String>,
+    /// The body.
+    pub body: Cow<'static, [u8]>,
+}
+
+impl Response {
+    /// Creates a response with status 200 and `body`.
+    pub fn new<B: Into<Cow<'static, [u8]>>>(body: B) -> Self {
+        Response {
+            status: 200,
+            headers: Vec::new(),
+            mime: None,
+            body: body.into(),
+        }
+    }
+
+    /// Creates an empty response with status 404.
+    pub fn not_found() -> Self {
+        Response::new(&[][..]).status(404)
+    }
+
+    /// Sets the HTTP status code.
+    pub fn status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Adds a header.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sets the MIME type.
+    pub fn mime(mut self, mime: &str) -> Self {
+        self.mime = Some(mime.to_string());
+        self
+    }
+
+    /// Applies the Content Security Policy `policy`, with a header and, as the header is lost with
+    /// older versions of WebKitGTK, a `<meta>` element in HTML pages.
+    pub(crate) fn with_csp(mut self, policy: &str) -> Self {
+        let is_html = self.mime.as_deref().map(|mime| mime.starts_with("text/html")).unwrap_or(false);
+
+        if is_html && self.status == 200 {
+            if let Ok(html) = std::str::from_utf8(&self.body) {
+                self.body = Cow::Owned(csp::insert_meta(html, policy).into_bytes());
+            }
+        }
+
+        self.header("Content-Security-Policy", policy)
+            .header("X-Content-Type-Options", "nosniff")
+    }
+
+    /// Completes the response to `request`, guessing its MIME type and applying the range
+    /// requested.
+    pub(crate) fn finish(mut self, request: &ProtocolRequest) -> Self {
+        if self.mime.is_none() {
+            self.mime = Some(mime::guess(&request.path()).to_string());
+        }
+
+        if self.status != 200 {
+            return self;
+        }
+
+        let len = self.body.len();
+        let range = match request.header("Range") {
+            Some(header) => parse_range(header, len),
+            None => Ok(None),
+        };
+
+        match range {
+            Ok(Some((start, end))) => {
+                self.body = match self.body {
+                    Cow::Borrowed(body) => Cow::Borrowed(&body[start..=end]),
+                    Cow::Owned(mut body) => {
+                        body.truncate(end + 1);
+                        body.drain(..start);
+                        Cow::Owned(body)
+                    },
+                };
+
+                self.status(206)
+                    .header("Accept-Ranges", "bytes")
+                    .header("Content-Range", &format!("bytes {}-{}/{}", start, end, len))
+            },
+            Ok(None) => self.header("Accept-Ranges", "bytes"),
+            Err(()) => {
+                self.body = Cow::Borrowed(&[]);
+                self.status(416).header("Content-Range", &format!("bytes */{}", len))
+            },
+        }
+    }
+}
+
+/// Parses a `Range` header into the first and last byte requested of a body of `len` bytes.
+/// Returns `None` for headers which are not supported, such as multiple ranges, and an error if
+/// the range cannot be satisfied.
+fn parse_range(header: &str, len: usize) -> Result<Option<(usize, usize)>, ()> {
+    let spec = match header.trim().strip_prefix("bytes=") {
+        Some(spec) if !spec.contains(',') => spec.trim(),
+        _ => return Ok(None),
+    };
+    let dash = match spec.find('-') {
+        Some(dash) => dash,
+        None => return Ok(None),
+    };
+    let (start, end) = (spec[..dash].trim(), spec[dash + 1..].trim());
+
+    let (start, end) = match (start.parse::<usize>(), end.parse::<usize>()) {
+        (Ok(start), Ok(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+        (Ok(start), Err(_)) if end.is_empty() => (start, len.saturating_sub(1)),
+        // A suffix, e.g. the last 500 bytes
+        (Err(_), Ok(suffix)) if start.is_empty() && suffix > 0 => (len.saturating_sub(suffix), len.saturating_sub(1)),
+        _ => return Ok(None),
+    };
+
+    if start >= len {
+        return Err(());
+    }
+
+    Ok(Some((start, end)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(uri: &str, range: Option<&str>) -> ProtocolRequest {
+        match range {
+            Some(range) => ProtocolRequest::new("GET", uri).with_header("range", range),
+            None => ProtocolRequest::new("GET", uri),
+        }
+    }
+
+    #[test]
+    fn test_path() {
+        assert_eq!("/", path("app://localhost"));
+        assert_eq!("/css/my style.css", path("app://localhost/css/my%20style.css?v=2#top"));
+        assert_eq!("/users", path("api://localhost/users?page=2"));
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(Ok(Some((0, 99))), parse_range("bytes=0-99", 1000));
+        assert_eq!(Ok(Some((500, 999))), parse_range("bytes=500-", 1000));
+        assert_eq!(Ok(Some((900, 999))), parse_range("bytes=-100", 1000));
+        assert_eq!(Ok(Some((0, 999))), parse_range("bytes=-2000", 1000));
+        assert_eq!(Ok(Some((990, 999))), parse_range("bytes=990-2000", 1000));
+        assert_eq!(Err(()), parse_range("bytes=1000-", 1000));
+        assert_eq!(Ok(None), parse_range("bytes=0-1, 5-6", 1000));
+        assert_eq!(Ok(None), parse_range("bytes=9-1", 1000));
+        assert_eq!(Ok(None), parse_range("items=0-1", 1000));
+    }
+
+    #[test]
+    fn test_finish() {
+        let body: &'static [u8] = b"0123456789";
+
+        let response = Response::new(body).finish(&request("api://localhost/clip.mp4", Some("bytes=2-4")));
+        assert_eq!(206, response.status);
+        assert_eq!(&b"234"[..], &*response.body);
+        assert_eq!(Some("video/mp4"), response.mime.as_deref());
+        assert!(response.headers.contains(&("Content-Range".to_string(), "bytes 2-4/10".to_string())));
+
+        let response = Response::new(body.to_vec()).finish(&request("api://localhost/a", Some("bytes=8-")));
+        assert_eq!(&b"89"[..], &*response.body);
+
+        let response = Response::new(body).mime("text/plain").finish(&request("api://localhost/a", None));
+        assert_eq!(200, response.status);
+        assert_eq!(Some("text/plain"), response.mime.as_deref());
+
+        let response = Response::new(body).finish(&request("api://localhost/a", Some("bytes=10-")));
+        assert_eq!(416, response.status);
+        assert!(response.body.is_empty());
+
+        let response = Response::not_found().finish(&request("api://localhost/a", Some("bytes=0-1")));
+        assert_eq!(404, response.status);
+
+        let response = Response::new(&b"<p>"[..])
+            .finish(&request("api://localhost/index.html", None))
+            .with_csp("img-src 'none'");
+        assert_eq!(
+            &b"<meta http-equiv=\"Content-Security-Policy\" content=\"img-src 'none'\"><p>"[..],
+            &*response.body
+        );
+        assert!(response.headers.contains(&("Content-Security-Policy".to_string(), "img-src 'none'".to_string())));
+    }
+}
@@ -0,0 +1,292 @@
+use crate::escape::to_js;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fs;
+use std::os::raw::c_void;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+use std::sync::{
+    Arc,
+    RwLock,
+    Weak,
+};
+use std::thread;
+use std::time::{
+    Duration,
+    SystemTime,
+};
+use webview_sys::{
+    webview_dispatch,
+    webview_eval,
+    webview_inject_css,
+    WebViewFFI,
+};
+
+/// How often files are checked for changes.
+const INTERVAL: Duration = Duration::from_millis(250);
+
+/// When and how large a file was last seen, telling apart changes made within the precision of
+/// modification times.
+type Stamp = (SystemTime, u64);
+
+/// What to do once files have changed.
+#[derive(Debug, PartialEq)]
+enum Change {
+    Reload,
+    /// Stylesheets, as paths relative to the root and their new content.
+    Css(Vec<(String, String)>),
+}
+
+/// Pointer to the WebView, only used while holding its live lock.
+struct View(*mut WebViewFFI);
+
+unsafe impl Send for View {}
+
+/// Watches the frontend of a WebView in debug mode, reloading the page when its files change or
+/// swapping stylesheets when only those did. Stops once dropped.
+pub struct Watcher {
+    stopped: Arc<AtomicBool>,
+}
+
+impl Watcher {
+    /// Starts polling the files of `root` on another thread, also those of its subdirectories if
+    /// `recursive`.
+    pub fn start(root: PathBuf, recursive: bool, view: *mut WebViewFFI, live: Weak<RwLock<()>>) -> Self {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let view = View(view);
+
+        {
+            let stopped = stopped.clone();
+
+            thread::spawn(move || {
+                let mut files = scan(&root, recursive);
+
+                while !stopped.load(Ordering::SeqCst) {
+                    thread::sleep(INTERVAL);
+
+                    let current = scan(&root, recursive);
+
+                    if let Some(change) = diff(&root, &files, &current) {
+                        if !dispatch(&view, &live, change) {
+                            break;
+                        }
+                    }
+
+                    files = current;
+                }
+            });
+        }
+
+        Watcher { stopped }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Applies `change` on the thread of the WebView. Returns `false` if it has been dropped.
+fn dispatch(view: &View, live: &Weak<RwLock<()>>, change: Change) -> bool {
+    let lock = match live.upgrade() {
+        Some(lock) => lock,
+        None => return false,
+    };
+    let _locked = match lock.read() {
+        Ok(locked) => locked,
+        Err(_) => return false,
+    };
+
+    unsafe {
+        webview_dispatch(view.0, Some(ffi_apply), Box::into_raw(Box::new(change)) as _);
+    }
+
+    true
+}
+
+unsafe extern "C" fn ffi_apply(ffi: *mut WebViewFFI, arg: *mut c_void) {
+    let change = Box::from_raw(arg as *mut Change);
+
+    match *change {
+        Change::Reload => eval(ffi, "location.reload()"),
+        Change::Css(sheets) => {
+            for (path, css) in sheets {
+                let path = match to_js(&path) {
+                    Ok(path) => path,
+                    Err(_) => continue,
+                };
+                let css = match CString::new(css) {
+                    Ok(css) => css,
+                    Err(_) => continue,
+                };
+
+                // Drop the last copy injected and disable the `<link>` it replaces, then tag the
+                // `<style>` element injected in its place
+                eval(ffi, &format!("({})({})", SWAP_CSS, path));
+                webview_inject_css(ffi, css.as_ptr());
+                eval(ffi, &format!("document.head.lastElementChild.setAttribute('data-webview-reload', {})", path));
+            }
+        },
+    }
+}
+
+const SWAP_CSS: &str = "function (path) {
+    var styles = document.querySelectorAll('style[data-webview-reload]');
+    var links = document.querySelectorAll('link[rel=stylesheet]');
+    var i, href;
+
+    for (i = 0; i < styles.length; i++) {
+        if (styles[i].getAttribute('data-webview-reload') === path) {
+            styles[i].parentNode.removeChild(styles[i]);
+        }
+    }
+
+    for (i = 0; i < links.length; i++) {
+        href = decodeURIComponent(new URL(links[i].href).pathname);
+
+        if (href.slice(-path.length - 1) === '/' + path) {
+            links[i].disabled = true;
+        }
+    }
+}";
+
+unsafe fn eval(ffi: *mut WebViewFFI, js: &str) {
+    if let Ok(js) = CString::new(js) {
+        webview_eval(ffi, js.as_ptr());
+    }
+}
+
+/// Returns the stamp of every file of `dir`, and of its subdirectories if `recursive`, skipping
+/// those [`ignored`].
+///
+/// [`ignored`]: fn.ignored.html
+fn scan(dir: &Path, recursive: bool) -> HashMap<PathBuf, Stamp> {
+    let mut files = HashMap::new();
+    let mut dirs = vec![dir.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+
+            if ignored(&name) {
+                continue;
+            }
+
+            match entry.metadata() {
+                Ok(metadata) if metadata.is_dir() => {
+                    if recursive {
+                        dirs.push(entry.path());
+                    }
+                },
+                Ok(metadata) => {
+                    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+
+                    files.insert(entry.path(), (modified, metadata.len()));
+                },
+                Err(_) => (),
+            }
+        }
+    }
+
+    files
+}
+
+/// Whether files named `name` are left out of scans: hidden files, `node_modules`, and those
+/// written by editors while saving, such as backups and swap files.
+fn ignored(name: &str) -> bool {
+    name.starts_with('.') || name == "node_modules" || name.ends_with('~') || name.ends_with(".swp") || name == "4913"
+}
+
+/// Compares two scans of `root`, returning what to do about the files which changed, if any.
+fn diff(root: &Path, before: &HashMap<PathBuf, Stamp>, after: &HashMap<PathBuf, Stamp>) -> Option<Change> {
+    let mut changed: Vec<&PathBuf> = after
+        .iter()
+        .filter(|(path, modified)| before.get(*path) != Some(modified))
+        .map(|(path, _)| path)
+        .collect();
+    let removed = before.keys().any(|path| !after.contains_key(path));
+
+    if changed.is_empty() && !removed {
+        return None;
+    }
+
+    changed.sort();
+
+    let mut sheets = Vec::new();
+
+    for path in changed {
+        let is_css = path.extension().map(|ext| ext.eq_ignore_ascii_case("css")).unwrap_or(false);
+        let relative = path.strip_prefix(root).ok().map(|path| path.to_string_lossy().replace('\\', "/"));
+
+        match (is_css, before.contains_key(path), relative, fs::read_to_string(path)) {
+            (true, true, Some(relative), Ok(css)) => sheets.push((relative, css)),
+            // New files, scripts and markup all need the page to be loaded again
+            _ => return Some(Change::Reload),
+        }
+    }
+
+    match removed {
+        true => Some(Change::Reload),
+        false => Some(Change::Css(sheets)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_diff() {
+        let root = env::temp_dir().join("webview reload test");
+        fs::create_dir_all(root.join("css")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("index.html"), "<html>").unwrap();
+        fs::write(root.join("css/style.css"), "p {}").unwrap();
+        fs::write(root.join(".git/HEAD"), "ref").unwrap();
+
+        let before = scan(&root, true);
+        assert_eq!(2, before.len());
+        assert_eq!(1, scan(&root, false).len());
+        assert_eq!(None, diff(&root, &before, &before));
+
+        let mut after = before.clone();
+        let (modified, _) = before[&root.join("css/style.css")];
+        // Same modification time, but the size changed
+        after.insert(root.join("css/style.css"), (modified, 5));
+        assert_eq!(
+            Some(Change::Css(vec![("css/style.css".to_string(), "p {}".to_string())])),
+            diff(&root, &before, &after)
+        );
+
+        after.insert(root.join("index.html"), (SystemTime::now() + Duration::from_secs(1), 6));
+        assert_eq!(Some(Change::Reload), diff(&root, &before, &after));
+
+        let mut after = before.clone();
+        after.remove(&root.join("index.html"));
+        assert_eq!(Some(Change::Reload), diff(&root, &before, &after));
+    }
+
+    #[test]
+    fn test_ignored() {
+        assert!(ignored(".git"));
+        assert!(ignored("node_modules"));
+        assert!(ignored("index.html~"));
+        assert!(ignored("4913"));
+        assert!(ignored("style.css.swp"));
+        assert!(!ignored("index.html"));
+    }
+}
@@ -0,0 +1,306 @@
+use crate::bridge::{
+    self,
+    Incoming,
+    Outgoing,
+};
+use crate::deferred::{
+    CancellationToken,
+    Deferred,
+};
+use crate::error::{
+    Error,
+    WVResult,
+};
+use crate::typescript::{
+    self,
+    Bindings,
+    Signature,
+    TypeScript,
+};
+use crate::WebView;
+use serde::de::{
+    self,
+    DeserializeOwned,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Returns the value resolving the call, or `None` if the response has been deferred.
+type Command<'a, T> = Box<dyn FnMut(&mut WebView<T>, &Request) -> WVResult<Option<Value>> + 'a>;
+
+/// A call made from JavaScript with `rust.call(name, args)`.
+///
+/// Every call carries an id which is used to settle the promise returned to JavaScript.
+#[derive(Debug)]
+pub struct Request {
+    id: u64,
+    name: String,
+    args: Value,
+}
+
+impl Request {
+    /// Parses a message received by an invoke handler.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Json`] if `message` was not sent by `rust.call`.
+    ///
+    /// [`Error::Json`]: enum.Error.html#variant.Json
+    pub fn parse(message: &str) -> WVResult<Request> {
+        match serde_json::from_str(message)? {
+            Incoming::Call { id, name, args } => Ok(Request { id, name, args }),
+            _ => Err(<serde_json::Error as de::Error>::custom("expected a call").into()),
+        }
+    }
+
+    /// Returns the id of the call, unique for the lifetime of the page.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the name passed to `rust.call`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Deserializes the arguments passed to `rust.call`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Json`] if the arguments do not match `A`.
+    ///
+    /// [`Error::Json`]: enum.Error.html#variant.Json
+    pub fn args<A: DeserializeOwned>(&self) -> WVResult<A> {
+        Ok(A::deserialize(&self.args)?)
+    }
+
+    /// Deserializes the arguments of a call to a registered command.
+    fn command_args<A: DeserializeOwned>(&self) -> WVResult<A> {
+        A::deserialize(&self.args).map_err(|cause| Error::InvalidArguments { command: self.name.clone(), cause })
+    }
+
+    /// Resolves or rejects the promise returned to JavaScript, then returns `result` unless the
+    /// error was forwarded to JavaScript.
+    pub(crate) fn respond<T>(&self, webview: &mut WebView<T>, result: WVResult<Value>) -> WVResult {
+        respond(webview, self.id, result)
+    }
+}
+
+/// Resolves or rejects the promise of the call `id`, then returns `result` unless the error was
+/// forwarded to JavaScript.
+pub(crate) fn respond<T>(webview: &mut WebView<T>, id: u64, result: WVResult<Value>) -> WVResult {
+    let message = match &result {
+        Ok(value) => Outgoing::Resolve { id, value },
+        Err(e) => Outgoing::Reject { id, error: e.into() },
+    };
+
+    bridge::send(webview, &message)?;
+
+    match result {
+        Err(ref e) if webview.forwards_error(e) => Ok(()),
+        result => result.map(|_| ()),
+    }
+}
+
+/// A set of named commands callable from JavaScript with `rust.call(name, args)`.
+///
+/// Usually populated through [`WebViewBuilder::command`].
+///
+/// [`WebViewBuilder::command`]: struct.WebViewBuilder.html#method.command
+pub struct Router<'a, T> {
+    commands: HashMap<String, Command<'a, T>>,
+    signatures: HashMap<String, Signature>,
+}
+
+impl<'a, T> Router<'a, T> {
+    /// Creates an empty router.
+    pub fn new() -> Self {
+        Router {
+            commands: HashMap::new(),
+            signatures: HashMap::new(),
+        }
+    }
+
+    /// Registers `handler` as the command `name`, replacing any previous command of that name.
+    pub fn add<A, R, F>(&mut self, name: &str, mut handler: F)
+    where
+        A: DeserializeOwned,
+        R: Serialize,
+        F: FnMut(&mut WebView<T>, A) -> WVResult<R> + 'a,
+    {
+        let command = Box::new(move |webview: &mut WebView<T>, request: &Request| {
+            let args = request.command_args()?;
+
+            Ok(Some(serde_json::to_value(handler(webview, args)?)?))
+        });
+
+        self.commands.insert(name.to_string(), command);
+        self.signatures.remove(name);
+    }
+
+    /// Registers `handler` as the command `name`, which settles the call through the [`Deferred`]
+    /// it is passed, possibly from another thread.
+    ///
+    /// [`Deferred`]: struct.Deferred.html
+    pub fn add_deferred<A, F>(&mut self, name: &str, mut handler: F)
+    where
+        A: DeserializeOwned,
+        F: FnMut(&mut WebView<T>, A, Deferred<T>) -> WVResult + 'a,
+    {
+        let command = Box::new(move |webview: &mut WebView<T>, request: &Request| {
+            let args = request.command_args()?;
+            let token = CancellationToken::new();
+
+            let page = webview.state_data_mut().page;
+
+            webview.state_data_mut().cancellations.insert(request.id, token.clone());
+
+            let result = handler(webview, args, Deferred::new(webview.handle(), request.id, page, token.clone()));
+
+            // The call is rejected with the error, so keep the deferred from settling it again,
+            // as it does once dropped
+            if result.is_err() {
+                let cancellations = &mut webview.state_data_mut().cancellations;
+
+                token.cancel();
+
+                if matches!(cancellations.get(&request.id), Some(t) if t.same(&token)) {
+                    cancellations.remove(&request.id);
+                }
+            }
+
+            result.map(|_| None)
+        });
+
+        self.commands.insert(name.to_string(), command);
+        self.signatures.remove(name);
+    }
+
+    /// Registers `handler` as the command `name` like [`add`], recording its argument and result
+    /// types for [`bindings`].
+    ///
+    /// [`add`]: struct.Router.html#method.add
+    /// [`bindings`]: struct.Router.html#method.bindings
+    pub fn add_typed<A, R, F>(&mut self, name: &str, handler: F)
+    where
+        A: DeserializeOwned + TypeScript,
+        R: Serialize + TypeScript,
+        F: FnMut(&mut WebView<T>, A) -> WVResult<R> + 'a,
+    {
+        self.add(name, handler);
+        self.signatures.insert(name.to_string(), typescript::signature::<A, R>);
+    }
+
+    /// Returns TypeScript bindings for the registered commands.
+    pub fn bindings(&self) -> Bindings {
+        Bindings::new(self.commands.keys().map(|name| (name.as_str(), self.signatures.get(name).cloned())))
+    }
+
+    /// Returns `true` if the command `name` has been registered.
+    pub fn contains(&self, name: &str) -> bool {
+        self.commands.contains_key(name)
+    }
+
+    /// Returns `true` if no commands have been registered.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Runs the command named by `request` and settles the promise returned to JavaScript.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnknownCommand`] if no command of that name has been registered, or the
+    /// error returned by the command.
+    ///
+    /// [`Error::UnknownCommand`]: enum.Error.html#variant.UnknownCommand
+    pub fn call(&mut self, webview: &mut WebView<T>, request: &Request) -> WVResult {
+        let result = match self.commands.get_mut(request.name()) {
+            Some(command) => command(webview, request),
+            None => Err(Error::UnknownCommand(request.name().to_string())),
+        };
+
+        match result {
+            Ok(Some(value)) => request.respond(webview, Ok(value)),
+            // Settled later through `Deferred`
+            Ok(None) => Ok(()),
+            Err(e) => request.respond(webview, Err(e)),
+        }
+    }
+}
+
+impl<'a, T> Default for Router<'a, T> {
+    fn default() -> Self {
+        Router::new()
+    }
+}
+
+/// Wraps `handler` into an invoke handler answering calls made with `rust.call(name, args)`.
+///
+/// The value returned by `handler` resolves the promise returned to JavaScript.
+///
+/// # Errors
+///
+/// If `handler` returns an `Err`, the promise is rejected with an `Error` carrying its variant name
+/// and message, and the error is returned on the next call to [`step()`] unless errors are
+/// forwarded to JavaScript (see [`WebViewBuilder::forward_errors`]).
+///
+/// # Example
+///
+/// ```no_run
+/// extern crate webview;
+///
+/// use webview::*;
+///
+/// fn main() {
+///     WebViewBuilder::new()
+///         .content(Content::Html("<script>rust.call('add', [1, 2]).then(console.log)</script>"))
+///         .user_data(())
+///         .invoke_handler(call_handler(|_webview, request| {
+///             let (a, b): (i32, i32) = request.args()?;
+///             Ok(a + b)
+///         }))
+///         .build()
+///         .unwrap()
+///         .run()
+///         .unwrap();
+/// }
+/// ```
+///
+/// [`step()`]: struct.WebView.html#method.step
+/// [`WebViewBuilder::forward_errors`]: struct.WebViewBuilder.html#method.forward_errors
+pub fn call_handler<'a, T, F, R>(mut handler: F) -> impl FnMut(&mut WebView<T>, &str) -> WVResult + 'a
+where
+    T: 'a,
+    F: FnMut(&mut WebView<T>, &Request) -> WVResult<R> + 'a,
+    R: Serialize,
+{
+    move |webview, message| {
+        let request = Request::parse(message)?;
+        let result = handler(webview, &request).and_then(|value| Ok(serde_json::to_value(value)?));
+
+        request.respond(webview, result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        let request = Request::parse(r#"{"__webview":"call","id":7,"name":"add","args":[1,2]}"#).unwrap();
+
+        assert_eq!(7, request.id());
+        assert_eq!("add", request.name());
+        assert_eq!((1, 2), request.args::<(i32, i32)>().unwrap());
+        assert!(request.args::<String>().is_err());
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(Request::parse("terminate").is_err());
+        assert!(Request::parse(r#"{"cmd":"init"}"#).is_err());
+    }
+}
@@ -0,0 +1,41 @@
+use crate::error::WVResult;
+use std::io;
+
+/// Returns 32 random hex digits, drawn from the randomness of the OS.
+pub fn random() -> WVResult<String> {
+    let mut bytes = [0u8; 16];
+
+    getrandom::getrandom(&mut bytes).map_err(io::Error::from)?;
+
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Compares `a` and `b` in a time which does not depend on where they differ, so that a secret
+/// cannot be guessed one character at a time.
+#[cfg(feature = "server")]
+pub fn eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_random() {
+        let secret = random().unwrap();
+
+        assert_eq!(32, secret.len());
+        assert!(secret.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(secret, random().unwrap());
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_eq() {
+        assert!(eq("abc", "abc"));
+        assert!(!eq("abc", "abd"));
+        assert!(!eq("abc", "ab"));
+        assert!(eq("", ""));
+    }
+}
@@ -0,0 +1,454 @@
+use crate::assets::Assets;
+use crate::content::{
+    serve_file,
+    Content,
+};
+use crate::error::WVResult;
+use crate::protocol::{
+    ProtocolRequest,
+    Response,
+};
+use crate::secret;
+use std::io::{
+    self,
+    BufRead,
+    BufReader,
+    Read,
+    Write,
+};
+use std::net::{
+    Ipv4Addr,
+    SocketAddr,
+    TcpListener,
+    TcpStream,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::atomic::{
+    AtomicBool,
+    AtomicUsize,
+    Ordering,
+};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Prefix of the name of the cookie holding the access token once the first page has been loaded,
+/// followed by the port, as cookies are shared by every port of a host.
+const COOKIE: &str = "webview-token";
+/// How long to wait for a client to send its request before dropping the connection.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest request line or header accepted.
+const MAX_LINE: usize = 16 * 1024;
+/// Most headers accepted in a request.
+const MAX_HEADERS: usize = 100;
+/// Largest request body accepted, in bytes.
+const MAX_BODY: u64 = 16 * 1024 * 1024;
+/// Most connections handled at once, each on its own thread. Others are closed unanswered.
+const MAX_CONNECTIONS: usize = 64;
+
+pub type ServerHandler = Arc<dyn Fn(&ProtocolRequest) -> Response + Send + Sync>;
+
+/// What the server answers requests with.
+enum Source {
+    Dir(PathBuf),
+    Html(String),
+    Assets(Assets),
+}
+
+impl Source {
+    fn respond(&self, request: &ProtocolRequest) -> Response {
+        match self {
+            Source::Dir(root) => serve_file(root, &request.path()),
+            Source::Html(html) if request.path() == "/" => {
+                Response::new(html.clone().into_bytes()).mime("text/html")
+            },
+            Source::Html(_) => Response::not_found(),
+            Source::Assets(assets) => assets.respond(request),
+        }
+    }
+}
+
+/// Loopback HTTP server for the content of a WebView, stopped once dropped.
+pub struct Server {
+    url: String,
+    addr: SocketAddr,
+    stopped: Arc<AtomicBool>,
+}
+
+impl Server {
+    /// Starts serving `content` on an ephemeral port of 127.0.0.1, answering requests for
+    /// anything else with `fallback`, and applying the Content Security Policy `csp` if any.
+    /// Returns `None` for `Content::Url`, which is loaded directly.
+    pub fn start<T: AsRef<str>>(
+        content: &Content<T>,
+        fallback: Option<ServerHandler>,
+        csp: Option<String>,
+    ) -> WVResult<Option<Self>> {
+        let (source, index) = match content {
+            Content::Url(_) => return Ok(None),
+            Content::Html(html) => (Source::Html(html.as_ref().to_string()), String::new()),
+            Content::Dir(path) => (Source::Dir(Path::new(path.as_ref()).canonicalize()?), String::new()),
+            Content::File(path) => {
+                let path = Path::new(path.as_ref()).canonicalize()?;
+                let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+                let root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+                (Source::Dir(root), name)
+            },
+            Content::Assets(assets) => (Source::Assets(*assets), assets.index.to_string()),
+        };
+
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let addr = listener.local_addr()?;
+        let token = secret::random()?;
+        let url = format!("http://{}/{}?token={}", addr, index, token);
+        let stopped = Arc::new(AtomicBool::new(false));
+        let source = Arc::new(source);
+        let csp = Arc::new(csp);
+
+        {
+            let stopped = stopped.clone();
+            let connections = Arc::new(AtomicUsize::new(0));
+
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stopped.load(Ordering::SeqCst) {
+                        break;
+                    }
+
+                    let stream = match stream {
+                        Ok(stream) => stream,
+                        Err(_) => continue,
+                    };
+                    let connection = match Connection::open(&connections) {
+                        Some(connection) => connection,
+                        None => continue,
+                    };
+                    let (source, fallback, token, csp) = (source.clone(), fallback.clone(), token.clone(), csp.clone());
+
+                    thread::spawn(move || {
+                        let _ = handle(stream, addr, &token, &source, fallback.as_ref(), csp.as_deref());
+                        drop(connection);
+                    });
+                }
+            });
+        }
+
+        Ok(Some(Server { url, addr, stopped }))
+    }
+
+    /// Returns the URL of the first page, holding the access token.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+
+        // Wake up the listener so that it sees it has been stopped
+        let _ = TcpStream::connect_timeout(&self.addr, Duration::from_secs(1));
+    }
+}
+
+/// A connection being handled, counted until dropped.
+struct Connection(Arc<AtomicUsize>);
+
+impl Connection {
+    /// Counts a new connection in `count`, unless `MAX_CONNECTIONS` are already being handled.
+    fn open(count: &Arc<AtomicUsize>) -> Option<Self> {
+        if count.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            count.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+
+        Some(Connection(count.clone()))
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Returns `true` if `host`, the `Host` header of a request, names the server at `addr`. Pages of
+/// other sites can get their own host name resolved to the loopback address, but not sent as this.
+fn host_allowed(host: Option<&str>, addr: SocketAddr) -> bool {
+    let port = addr.port();
+
+    matches!(host, Some(host) if host == format!("127.0.0.1:{}", port)
+        || host.eq_ignore_ascii_case(&format!("localhost:{}", port)))
+}
+
+/// Returns the name of the cookie holding the access token of the server at `addr`.
+fn cookie_name(addr: SocketAddr) -> String {
+    format!("{}-{}", COOKIE, addr.port())
+}
+
+/// Answers a single request made over `stream`.
+fn handle(
+    stream: TcpStream,
+    addr: SocketAddr,
+    token: &str,
+    source: &Source,
+    fallback: Option<&ServerHandler>,
+    csp: Option<&str>,
+) -> io::Result<()> {
+    // Clients which never finish their request would otherwise hold a thread forever
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let request_line = read_line(&mut reader)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) if target.starts_with('/') => (method.to_string(), target.to_string()),
+        _ => return write_response(stream, Response::new(&[][..]).status(400), false, None),
+    };
+    let mut request = ProtocolRequest::new(&method, &format!("http://{}{}", addr, target));
+    let mut length = 0;
+
+    for _ in 0..MAX_HEADERS {
+        let line = read_line(&mut reader)?;
+
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some(colon) = line.find(':') {
+            let (name, value) = (line[..colon].trim(), line[colon + 1..].trim());
+
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.parse().unwrap_or(0);
+            }
+
+            request = request.with_header(name, value);
+        }
+    }
+
+    // Requests of pages of other sites rebound to the loopback address carry their own host
+    if !host_allowed(request.header("Host"), addr) {
+        return write_response(stream, Response::new(&b"Forbidden"[..]).status(403).mime("text/plain"), false, None);
+    }
+
+    let cookie = cookie_name(addr);
+    let from_query = matches!(query_token(&target), Some(t) if secret::eq(t, token));
+    let from_cookie = request
+        .header("Cookie")
+        .map(|cookies| cookies.split(';').any(|c| matches!(cookie_value(c, &cookie), Some(t) if secret::eq(t, token))))
+        .unwrap_or(false);
+
+    // Check the token before reading a body of any size
+    if !from_query && !from_cookie {
+        return write_response(stream, Response::new(&b"Forbidden"[..]).status(403).mime("text/plain"), false, None);
+    }
+
+    if length > MAX_BODY {
+        return write_response(stream, Response::new(&[][..]).status(413), false, None);
+    }
+
+    let mut body = Vec::new();
+    reader.take(length).read_to_end(&mut body)?;
+    let request = request.with_body(body);
+
+    let mut response = source.respond(&request);
+
+    if response.status == 404 {
+        if let Some(fallback) = fallback {
+            response = fallback(&request);
+        }
+    }
+
+    let response = match csp {
+        Some(csp) => response.finish(&request).with_csp(csp),
+        None => response.finish(&request),
+    };
+    // Later requests from the page carry the token in a cookie
+    let cookie = if from_query { Some((cookie.as_str(), token)) } else { None };
+
+    write_response(stream, response, method == "HEAD", cookie)
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> io::Result<String> {
+    let mut line = String::new();
+
+    reader.take(MAX_LINE as u64).read_line(&mut line)?;
+
+    if !line.ends_with('\n') {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "request line too long or truncated"));
+    }
+
+    Ok(line.trim_end().to_string())
+}
+
+/// Returns the value of `pair`, one of the `name=value` pairs of a `Cookie` header, if named `name`.
+fn cookie_value<'a>(pair: &'a str, name: &str) -> Option<&'a str> {
+    let pair = pair.trim();
+    let eq = pair.find('=')?;
+
+    match &pair[..eq] == name {
+        true => Some(&pair[eq + 1..]),
+        false => None,
+    }
+}
+
+/// Returns the value of the `token` query parameter of `target`.
+fn query_token(target: &str) -> Option<&str> {
+    let query = &target[target.find('?')? + 1..];
+
+    query
+        .split('&')
+        .find(|pair| pair.starts_with("token="))
+        .map(|pair| &pair["token=".len()..])
+}
+
+fn write_response(mut stream: TcpStream, response: Response, head: bool, cookie: Option<(&str, &str)>) -> io::Result<()> {
+    let mut out = format!("HTTP/1.1 {} {}\r\n", response.status, reason(response.status));
+
+    out.push_str(&format!("Content-Length: {}\r\n", response.body.len()));
+    out.push_str("Connection: close\r\n");
+
+    if let Some(mime) = &response.mime {
+        out.push_str(&format!("Content-Type: {}\r\n", mime));
+    }
+
+    if let Some((name, token)) = cookie {
+        out.push_str(&format!("Set-Cookie: {}={}; Path=/; HttpOnly; SameSite=Strict\r\n", name, token));
+    }
+
+    for (name, value) in &response.headers {
+        // Guard against header injection
+        if !name.contains(&['\r', '\n'][..]) && !value.contains(&['\r', '\n'][..]) {
+            out.push_str(&format!("{}: {}\r\n", name, value));
+        }
+    }
+
+    out.push_str("\r\n");
+    stream.write_all(out.as_bytes())?;
+
+    if !head {
+        stream.write_all(&response.body)?;
+    }
+
+    stream.flush()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        206 => "Partial Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        416 => "Range Not Satisfiable",
+        500 => "Internal Server Error",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get(url: &str, cookie: Option<&str>) -> String {
+        let rest = &url["http://".len()..];
+
+        get_from(url, &rest[..rest.find('/').unwrap()], cookie)
+    }
+
+    fn get_from(url: &str, host: &str, cookie: Option<&str>) -> String {
+        let rest = &url["http://".len()..];
+        let slash = rest.find('/').unwrap();
+        let mut stream = TcpStream::connect(&rest[..slash]).unwrap();
+        let cookie = cookie.map(|c| format!("Cookie: {}\r\n", c)).unwrap_or_default();
+
+        write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\n{}\r\n", &rest[slash..], host, cookie).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_query_token() {
+        assert_eq!(Some("abc"), query_token("/index.html?a=1&token=abc"));
+        assert_eq!(None, query_token("/index.html?xtoken=abc"));
+        assert_eq!(None, query_token("/index.html"));
+    }
+
+    #[test]
+    fn test_host_allowed() {
+        let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 8080));
+
+        assert!(host_allowed(Some("127.0.0.1:8080"), addr));
+        assert!(host_allowed(Some("LocalHost:8080"), addr));
+        assert!(!host_allowed(Some("localhost:8081"), addr));
+        assert!(!host_allowed(Some("localhost.evil.org:8080"), addr));
+        assert!(!host_allowed(None, addr));
+    }
+
+    #[test]
+    fn test_connection() {
+        let count = Arc::new(AtomicUsize::new(MAX_CONNECTIONS - 1));
+        let connection = Connection::open(&count);
+
+        assert!(connection.is_some());
+        assert!(Connection::open(&count).is_none());
+        drop(connection);
+        assert_eq!(MAX_CONNECTIONS - 1, count.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_cookie_value() {
+        assert_eq!(Some("abc"), cookie_value(" webview-token-80=abc", "webview-token-80"));
+        assert_eq!(None, cookie_value("webview-token-81=abc", "webview-token-80"));
+        assert_eq!(None, cookie_value("webview-token-80", "webview-token-80"));
+    }
+
+    #[test]
+    fn test_server() {
+        let fallback: ServerHandler = Arc::new(|request| match request.path().as_str() {
+            "/api/ping" => Response::new(&b"pong"[..]),
+            _ => Response::not_found(),
+        });
+        let csp = Some("script-src 'none'".to_string());
+        let server = Server::start(&Content::Html("<p>hi</p>"), Some(fallback), csp).unwrap().unwrap();
+        let url = server.url().to_string();
+        let token = query_token(&url).unwrap().to_string();
+        let base = &url[..url.find('?').unwrap()];
+        let name = cookie_name(server.addr);
+
+        let response = get(&url, None);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains(&format!("Set-Cookie: {}={};", name, token)));
+        assert!(response.contains("Content-Security-Policy: script-src 'none'\r\n"));
+        assert!(response.ends_with("content=\"script-src 'none'\"><p>hi</p>"));
+
+        let cookie = format!("{}={}", name, token);
+        assert!(get(&format!("{}api/ping", base), Some(&cookie)).ends_with("\r\n\r\npong"));
+        assert!(get(&format!("{}missing", base), Some(&cookie)).starts_with("HTTP/1.1 404"));
+        assert!(get(base, None).starts_with("HTTP/1.1 403"));
+        assert!(get(base, Some(&format!("{}=guess", name))).starts_with("HTTP/1.1 403"));
+        // The token of the server on another port is of no use
+        assert!(get(base, Some(&format!("{}-{}={}", COOKIE, server.addr.port() ^ 1, token))).starts_with("HTTP/1.1 403"));
+        // Nor is it when sent to another host, even one resolving to the loopback address
+        let port = server.addr.port();
+        assert!(get_from(&url, &format!("localhost:{}", port), None).starts_with("HTTP/1.1 200"));
+        assert!(get_from(&url, &format!("evil.org:{}", port), None).starts_with("HTTP/1.1 403"));
+        assert!(get_from(&url, "localhost", None).starts_with("HTTP/1.1 403"));
+
+        drop(server);
+        assert!(Server::start(&Content::Url("https://example.com"), None, None).unwrap().is_none());
+    }
+}
@@ -0,0 +1,149 @@
+use crate::error::{
+    Error,
+    WVResult,
+};
+use std::ffi::CString;
+use std::ptr;
+use webview_sys::{
+    webview_apply_settings,
+    WebViewFFI,
+};
+
+/// Browser settings of a WebView, set with [`WebViewBuilder::settings`].
+///
+/// Settings can only be changed with WebKitGTK.
+///
+/// # Example
+///
+/// ```
+/// # use webview::*;
+/// // A locked-down view of static pages
+/// let settings = WebSettings::new()
+///     .user_agent("MyApp/1.0")
+///     .javascript(false)
+///     .local_storage(false)
+///     .webgl(false)
+///     .autoplay(false);
+///
+/// assert_ne!(WebSettings::default(), settings);
+/// ```
+///
+/// [`WebViewBuilder::settings`]: struct.WebViewBuilder.html#method.settings
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WebSettings {
+    /// The user agent, or `None` for the one of the platform.
+    pub user_agent: Option<String>,
+    /// Whether scripts of the page run, `true` by default. The scripts of this library, and those
+    /// run with [`WebView::eval`], run either way with WebKitGTK 2.24 and later.
+    ///
+    /// [`WebView::eval`]: struct.WebView.html#method.eval
+    pub javascript: bool,
+    /// Whether pages can use `localStorage`, `true` by default.
+    pub local_storage: bool,
+    /// Whether pages can use WebGL, `true` by default.
+    pub webgl: bool,
+    /// Whether media plays without the user interacting with the page first, `true` by default.
+    pub autoplay: bool,
+    /// The default font size in pixels, or `None` for the one of the platform.
+    pub default_font_size: Option<u32>,
+    /// The default size of monospace fonts in pixels, or `None` for the one of the platform.
+    pub monospace_font_size: Option<u32>,
+    /// Whether pages loaded from `file://` URLs can load other `file://` URLs with scripts,
+    /// `false` by default.
+    pub file_access_from_files: bool,
+}
+
+impl Default for WebSettings {
+    fn default() -> Self {
+        WebSettings {
+            user_agent: None,
+            javascript: true,
+            local_storage: true,
+            webgl: true,
+            autoplay: true,
+            default_font_size: None,
+            monospace_font_size: None,
+            file_access_from_files: false,
+        }
+    }
+}
+
+impl WebSettings {
+    /// Alias for `WebSettings::default()`.
+    pub fn new() -> Self {
+        WebSettings::default()
+    }
+
+    /// Sets the user agent.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// Enables or disables the scripts of the page.
+    pub fn javascript(mut self, javascript: bool) -> Self {
+        self.javascript = javascript;
+        self
+    }
+
+    /// Enables or disables `localStorage`.
+    pub fn local_storage(mut self, local_storage: bool) -> Self {
+        self.local_storage = local_storage;
+        self
+    }
+
+    /// Enables or disables WebGL.
+    pub fn webgl(mut self, webgl: bool) -> Self {
+        self.webgl = webgl;
+        self
+    }
+
+    /// Enables or disables playing media before the user interacts with the page.
+    pub fn autoplay(mut self, autoplay: bool) -> Self {
+        self.autoplay = autoplay;
+        self
+    }
+
+    /// Sets the default font size in pixels.
+    pub fn default_font_size(mut self, size: u32) -> Self {
+        self.default_font_size = Some(size);
+        self
+    }
+
+    /// Sets the default size of monospace fonts in pixels.
+    pub fn monospace_font_size(mut self, size: u32) -> Self {
+        self.monospace_font_size = Some(size);
+        self
+    }
+
+    /// Allows or forbids pages loaded from `file://` URLs to load other `file://` URLs with
+    /// scripts.
+    pub fn file_access_from_files(mut self, allow: bool) -> Self {
+        self.file_access_from_files = allow;
+        self
+    }
+
+    /// Applies the settings to the WebView `ffi`.
+    pub(crate) fn apply(&self, ffi: *mut WebViewFFI) -> WVResult {
+        let user_agent = self.user_agent.as_deref().map(CString::new).transpose()?;
+        let font_size = |size: Option<u32>| size.map(|size| size.min(i32::MAX as u32) as i32).unwrap_or(0);
+        let ret = unsafe {
+            webview_apply_settings(
+                ffi,
+                user_agent.as_ref().map(|ua| ua.as_ptr()).unwrap_or(ptr::null()),
+                self.javascript as _,
+                self.local_storage as _,
+                self.webgl as _,
+                self.autoplay as _,
+                font_size(self.default_font_size),
+                font_size(self.monospace_font_size),
+                self.file_access_from_files as _,
+            )
+        };
+
+        match ret {
+            0 => Ok(()),
+            _ => Err(Error::Unsupported("web settings")),
+        }
+    }
+}
@@ -1,24 +1,110 @@
+use std::collections::HashMap;
 use std::sync::{
     Arc,
+    Mutex,
     RwLock,
 };
+use serde_json::Value;
+use crate::allowlist::Allowlist;
+use crate::batch::Batch;
+use crate::bridge::Partial;
+use crate::deferred::CancellationToken;
+use crate::dispatch::DispatchQueue;
 use crate::error::WVResult;
+use crate::load::LoadEvent;
+use crate::reload::Watcher;
+use crate::navigation::{
+    Decision,
+    NavigationKind,
+};
+use crate::protocol::ProtocolHandler;
+#[cfg(feature = "server")]
+use crate::server::Server;
 use crate::WebView;
 
+pub type InvokeHandler<'a, T> = Box<dyn FnMut(&mut WebView<T>, &str) -> WVResult + 'a>;
+pub type LoadHandler<'a, T> = Box<dyn FnMut(&mut WebView<T>, &LoadEvent) -> WVResult + 'a>;
+pub type DomContentLoadedHandler<'a, T> = Box<dyn FnMut(&mut WebView<T>, &str) -> WVResult + 'a>;
+pub type NavigationHandler<'a> = Box<dyn FnMut(&str, NavigationKind) -> Decision + 'a>;
+pub type EvalCallback<T> = Box<dyn FnOnce(&mut WebView<T>, WVResult<Value>) -> WVResult>;
+
 pub struct WebViewStateData<'a, T> {
     live: Arc<RwLock<()>>,
     internal: Arc<RwLock<T>>,
-    pub invoke_handler: Box<FnMut(&mut WebView<T>, &str) -> WVResult + 'a>,
+    pub invoke_handler: InvokeHandler<'a, T>,
     pub result: Option<WVResult>,
+    pub forward_errors: bool,
+    pub pending_evals: HashMap<u64, EvalCallback<T>>,
+    pub next_eval_id: u64,
+    pub chunks: Option<Partial>,
+    pub next_chunk_id: u64,
+    pub cancellations: HashMap<u64, CancellationToken>,
+    /// Incremented whenever a page is replaced by another, telling apart calls of different pages.
+    pub page: u64,
+    pub allowlist: Allowlist<'a>,
+    pub content_url: String,
+    /// The URL of the page last committed, which sent the messages being handled.
+    pub page_url: String,
+    /// Prefix of the messages sent by the bridge script, which frames within the page do not know.
+    pub frame_key: String,
+    pub protocols: HashMap<String, ProtocolHandler<'a>>,
+    /// The scheme whose handler serves the content, rather than one set by the user.
+    pub content_scheme: Option<&'static str>,
+    pub init_scripts: Vec<(u64, String)>,
+    pub load_handler: Option<LoadHandler<'a, T>>,
+    pub dom_content_loaded_handler: Option<DomContentLoadedHandler<'a, T>>,
+    pub navigation_handler: Option<NavigationHandler<'a>>,
+    /// Whether the platform reports page loads, rather than the bridge script.
+    pub native_load_events: bool,
+    /// Whether the bridge script runs on every page, which only WebKitGTK supports.
+    pub bridge: bool,
+    /// Whether the bridge script reported `DOMContentLoaded` for the current page.
+    pub dom_content_loaded: bool,
+    pub next_script_id: u64,
+    pub watcher: Option<Watcher>,
+    pub csp: Option<String>,
+    #[cfg(feature = "server")]
+    pub server: Option<Server>,
+    pub batch: Arc<Mutex<Batch>>,
+    pub dispatch_queue: Arc<DispatchQueue<T>>,
+    pub max_message_size: Option<usize>,
 }
 
 impl<'a, T> WebViewStateData<'a, T> {
-    pub fn new(data: T, invoke_handler: Box<FnMut(&mut WebView<T>, &str) -> WVResult + 'a>) -> Self {
+    pub fn new(data: T, invoke_handler: InvokeHandler<'a, T>) -> Self {
         Self {
             live: Arc::new(RwLock::new(())),
             internal: Arc::new(RwLock::new(data)),
             invoke_handler,
             result: None,
+            forward_errors: false,
+            pending_evals: HashMap::new(),
+            next_eval_id: 0,
+            chunks: None,
+            next_chunk_id: 0,
+            cancellations: HashMap::new(),
+            page: 0,
+            allowlist: Allowlist::new(),
+            content_url: String::new(),
+            page_url: String::new(),
+            frame_key: String::new(),
+            protocols: HashMap::new(),
+            content_scheme: None,
+            init_scripts: Vec::new(),
+            load_handler: None,
+            dom_content_loaded_handler: None,
+            native_load_events: false,
+            bridge: false,
+            dom_content_loaded: false,
+            navigation_handler: None,
+            watcher: None,
+            csp: None,
+            next_script_id: 0,
+            #[cfg(feature = "server")]
+            server: None,
+            batch: Arc::new(Mutex::new(Batch::default())),
+            dispatch_queue: Arc::new(DispatchQueue::new(None)),
+            max_message_size: None,
         }
     }
 
@@ -29,4 +115,37 @@ impl<'a, T> WebViewStateData<'a, T> {
     pub fn live_lock(&self) -> Arc<RwLock<()>> {
         self.live.clone()
     }
+
+    /// Records `result` to be returned by the next call to `step()`, unless an error which has not
+    /// been returned yet was recorded before, so that it is never lost.
+    pub fn set_result(&mut self, result: WVResult) {
+        if !matches!(self.result, Some(Err(_))) {
+            self.result = Some(result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::Error;
+
+    #[test]
+    fn test_set_result() {
+        let mut data = WebViewStateData::new((), Box::new(|_, _| Ok(())));
+
+        data.set_result(Ok(()));
+        data.set_result(Err(Error::Dispatch));
+        // A later success or error does not replace the first error
+        data.set_result(Ok(()));
+        data.set_result(Err(Error::QueueFull));
+
+        match data.result.take() {
+            Some(Err(Error::Dispatch)) => (),
+            r => panic!("Unexpected result {:?}", r),
+        }
+
+        data.set_result(Ok(()));
+        assert!(matches!(data.result, Some(Ok(()))));
+    }
 }
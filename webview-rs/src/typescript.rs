@@ -0,0 +1,454 @@
+use serde_json::Value;
+use std::collections::{
+    BTreeMap,
+    HashMap,
+    HashSet,
+};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const HEADER: &str = "// Generated by webview. Do not edit.\n";
+
+/// Words which cannot name a function in a JavaScript module, which is strict mode code.
+const RESERVED: &[&str] = &[
+    "arguments", "await", "break", "case", "catch", "class", "const", "continue", "debugger",
+    "default", "delete", "do", "else", "enum", "eval", "export", "extends", "false", "finally",
+    "for", "function", "if", "implements", "import", "in", "instanceof", "interface", "let", "new",
+    "null", "package", "private", "protected", "public", "return", "static", "super", "switch",
+    "this", "throw", "true", "try", "typeof", "var", "void", "while", "with", "yield",
+];
+
+/// A type with a TypeScript equivalent, used to generate bindings for commands registered with
+/// [`WebViewBuilder::typed_command`].
+///
+/// Implement it for your own structs with [`ts_interface!`].
+///
+/// [`WebViewBuilder::typed_command`]: struct.WebViewBuilder.html#method.typed_command
+/// [`ts_interface!`]: macro.ts_interface.html
+pub trait TypeScript {
+    /// Returns the TypeScript type, e.g. `string` or `Task[]`.
+    fn ts_type() -> String;
+
+    /// Adds the declarations the type refers to, such as its own interface, to `declarations`.
+    fn ts_declare(_declarations: &mut Declarations) {}
+}
+
+/// Named TypeScript declarations, such as interfaces, emitted alongside generated bindings.
+#[derive(Debug, Default)]
+pub struct Declarations {
+    declarations: BTreeMap<String, String>,
+}
+
+impl Declarations {
+    /// Adds `declaration` under `name`. Returns `false`, leaving the existing declaration in place,
+    /// if `name` was already declared.
+    pub fn declare(&mut self, name: &str, declaration: String) -> bool {
+        if self.declarations.contains_key(name) {
+            return false;
+        }
+
+        self.declarations.insert(name.to_string(), declaration);
+        true
+    }
+}
+
+/// Implements [`TypeScript`] for a struct, declaring it as an interface with the listed fields.
+///
+/// The fields must match those of the struct exactly, or the invocation fails to compile. Field
+/// names are used as-is, so the struct should use serde's default field naming.
+///
+/// # Example
+///
+/// ```
+/// #[macro_use]
+/// extern crate serde_derive;
+/// #[macro_use]
+/// extern crate webview;
+///
+/// #[derive(Deserialize)]
+/// struct MarkTask {
+///     index: usize,
+///     done: bool,
+/// }
+///
+/// ts_interface!(MarkTask { index: usize, done: bool });
+///
+/// # fn main() {}
+/// ```
+///
+/// [`TypeScript`]: trait.TypeScript.html
+#[macro_export]
+macro_rules! ts_interface {
+    ($name:ident { $($field:ident : $ty:ty),* $(,)* }) => {
+        impl $crate::TypeScript for $name {
+            fn ts_type() -> ::std::string::String {
+                ::std::string::String::from(stringify!($name))
+            }
+
+            fn ts_declare(declarations: &mut $crate::Declarations) {
+                let mut fields = ::std::string::String::new();
+
+                $(
+                    fields.push_str(&format!("  {}: {};\n", stringify!($field), <$ty as $crate::TypeScript>::ts_type()));
+                )*
+
+                if declarations.declare(stringify!($name), format!("export interface {} {{\n{}}}", stringify!($name), fields)) {
+                    $( <$ty as $crate::TypeScript>::ts_declare(declarations); )*
+                }
+            }
+        }
+
+        const _: () = {
+            // Fails to compile unless the listed fields match those of the struct
+            #[allow(dead_code)]
+            fn check(value: $name) {
+                let $name { $($field),* } = value;
+                $( let _: $ty = $field; )*
+            }
+        };
+    };
+}
+
+macro_rules! impl_primitive {
+    ($ts:expr => $($ty:ty),*) => {
+        $(
+            impl TypeScript for $ty {
+                fn ts_type() -> String {
+                    $ts.to_string()
+                }
+            }
+        )*
+    };
+}
+
+impl_primitive!("boolean" => bool);
+impl_primitive!("number" => i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+impl_primitive!("string" => str, String, char);
+impl_primitive!("null" => ());
+impl_primitive!("unknown" => Value);
+
+/// Parenthesizes union types so that they can be followed by `[]`.
+fn element(ts: String) -> String {
+    if ts.contains('|') {
+        format!("({})", ts)
+    } else {
+        ts
+    }
+}
+
+impl<T: TypeScript + ?Sized> TypeScript for &T {
+    fn ts_type() -> String {
+        T::ts_type()
+    }
+
+    fn ts_declare(declarations: &mut Declarations) {
+        T::ts_declare(declarations)
+    }
+}
+
+impl<T: TypeScript + ?Sized> TypeScript for Box<T> {
+    fn ts_type() -> String {
+        T::ts_type()
+    }
+
+    fn ts_declare(declarations: &mut Declarations) {
+        T::ts_declare(declarations)
+    }
+}
+
+impl<T: TypeScript> TypeScript for Option<T> {
+    fn ts_type() -> String {
+        format!("{} | null", T::ts_type())
+    }
+
+    fn ts_declare(declarations: &mut Declarations) {
+        T::ts_declare(declarations)
+    }
+}
+
+impl<T: TypeScript> TypeScript for [T] {
+    fn ts_type() -> String {
+        format!("{}[]", element(T::ts_type()))
+    }
+
+    fn ts_declare(declarations: &mut Declarations) {
+        T::ts_declare(declarations)
+    }
+}
+
+impl<T: TypeScript> TypeScript for Vec<T> {
+    fn ts_type() -> String {
+        <[T]>::ts_type()
+    }
+
+    fn ts_declare(declarations: &mut Declarations) {
+        T::ts_declare(declarations)
+    }
+}
+
+impl<T: TypeScript, S> TypeScript for HashSet<T, S> {
+    fn ts_type() -> String {
+        <[T]>::ts_type()
+    }
+
+    fn ts_declare(declarations: &mut Declarations) {
+        T::ts_declare(declarations)
+    }
+}
+
+impl<K: ToString, V: TypeScript, S> TypeScript for HashMap<K, V, S> {
+    fn ts_type() -> String {
+        format!("{{ [key: string]: {} }}", V::ts_type())
+    }
+
+    fn ts_declare(declarations: &mut Declarations) {
+        V::ts_declare(declarations)
+    }
+}
+
+impl<K: ToString, V: TypeScript> TypeScript for BTreeMap<K, V> {
+    fn ts_type() -> String {
+        <HashMap<K, V>>::ts_type()
+    }
+
+    fn ts_declare(declarations: &mut Declarations) {
+        V::ts_declare(declarations)
+    }
+}
+
+macro_rules! impl_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: TypeScript),+> TypeScript for ($($name,)+) {
+            fn ts_type() -> String {
+                format!("[{}]", vec![$($name::ts_type()),+].join(", "))
+            }
+
+            fn ts_declare(declarations: &mut Declarations) {
+                $($name::ts_declare(declarations);)+
+            }
+        }
+    };
+}
+
+impl_tuple!(A);
+impl_tuple!(A, B);
+impl_tuple!(A, B, C);
+impl_tuple!(A, B, C, D);
+impl_tuple!(A, B, C, D, E);
+impl_tuple!(A, B, C, D, E, F);
+
+/// Returns the TypeScript types of a command's arguments and result, declaring those they refer to.
+pub type Signature = fn(&mut Declarations) -> (String, String);
+
+pub fn signature<A: TypeScript, R: TypeScript>(declarations: &mut Declarations) -> (String, String) {
+    A::ts_declare(declarations);
+    R::ts_declare(declarations);
+
+    (A::ts_type(), R::ts_type())
+}
+
+/// TypeScript declarations and a JavaScript client module for registered commands.
+///
+/// Commands registered without types (with [`WebViewBuilder::command`]) take and return
+/// `unknown`.
+///
+/// # Example
+///
+/// Keeping the bindings of a frontend up to date from a test:
+///
+/// ```no_run
+/// #[macro_use]
+/// extern crate serde_derive;
+/// #[macro_use]
+/// extern crate webview;
+///
+/// use webview::*;
+///
+/// #[derive(Deserialize)]
+/// struct AddTask {
+///     name: String,
+/// }
+///
+/// ts_interface!(AddTask { name: String });
+///
+/// fn builder<'a>() -> WebViewBuilder<'a, Vec<String>, &'static str> {
+///     WebViewBuilder::new()
+///         .typed_command("addTask", |webview: &mut WebView<Vec<String>>, args: AddTask| {
+///             let tasks = webview.user_data();
+///             let mut tasks = tasks.write().unwrap();
+///
+///             tasks.push(args.name);
+///             Ok(tasks.len())
+///         })
+/// }
+///
+/// #[test]
+/// fn bindings() {
+///     builder().bindings().write("frontend/src", "rust").unwrap();
+/// }
+/// # fn main() {}
+/// ```
+///
+/// [`WebViewBuilder::command`]: struct.WebViewBuilder.html#method.command
+#[derive(Debug)]
+pub struct Bindings {
+    commands: Vec<(String, String, String)>,
+    declarations: Declarations,
+}
+
+impl Bindings {
+    pub(crate) fn new<'b, I>(commands: I) -> Self
+    where
+        I: IntoIterator<Item = (&'b str, Option<Signature>)>,
+    {
+        let mut declarations = Declarations::default();
+        let mut commands: Vec<_> = commands
+            .into_iter()
+            .map(|(name, signature)| {
+                let (args, result) = match signature {
+                    Some(signature) => signature(&mut declarations),
+                    None => ("unknown".to_string(), "unknown".to_string()),
+                };
+
+                (name.to_string(), args, result)
+            })
+            .collect();
+
+        commands.sort();
+
+        Bindings {
+            commands,
+            declarations,
+        }
+    }
+
+    /// Returns the contents of a `.d.ts` file declaring the client module.
+    pub fn declarations(&self) -> String {
+        let mut ts = String::from(HEADER);
+
+        for declaration in self.declarations.declarations.values() {
+            ts.push_str(&format!("\n{}\n", declaration));
+        }
+
+        ts.push_str("\nexport interface Commands {\n");
+
+        for (name, args, result) in &self.commands {
+            ts.push_str(&format!("  {:?}: {{ args: {}; result: {} }};\n", name, args, result));
+        }
+
+        ts.push_str("}\n\n");
+        ts.push_str("export declare function call<K extends keyof Commands>(name: K, args: Commands[K][\"args\"]): Promise<Commands[K][\"result\"]>;\n");
+
+        for (name, args, result) in self.functions() {
+            let optional = if args == "null" { "?" } else { "" };
+
+            ts.push_str(&format!("export declare function {}(args{}: {}): Promise<{}>;\n", name, optional, args, result));
+        }
+
+        ts
+    }
+
+    /// Returns the contents of the JavaScript client module, with a function for each command.
+    pub fn client(&self) -> String {
+        let mut js = String::from(HEADER);
+
+        js.push_str("\nexport function call(name, args) {\n  return window.rust.call(name, args);\n}\n");
+
+        for (name, _, _) in self.functions() {
+            js.push_str(&format!("\nexport function {0}(args) {{\n  return call({0:?}, args);\n}}\n", name));
+        }
+
+        js
+    }
+
+    /// Writes [`declarations()`] and [`client()`] to `<name>.d.ts` and `<name>.js` in `dir`.
+    ///
+    /// [`declarations()`]: struct.Bindings.html#method.declarations
+    /// [`client()`]: struct.Bindings.html#method.client
+    pub fn write<P: AsRef<Path>>(&self, dir: P, name: &str) -> io::Result<()> {
+        let dir = dir.as_ref();
+
+        fs::write(dir.join(format!("{}.d.ts", name)), self.declarations())?;
+        fs::write(dir.join(format!("{}.js", name)), self.client())
+    }
+
+    /// Commands whose names are valid JavaScript identifiers, which get their own function. Those
+    /// named after reserved words, or `call` itself, can only be called through `call`.
+    fn functions(&self) -> impl Iterator<Item = &(String, String, String)> {
+        self.commands.iter().filter(|(name, _, _)| {
+            let mut chars = name.chars();
+
+            if name == "call" || RESERVED.contains(&name.as_str()) {
+                return false;
+            }
+
+            match chars.next() {
+                Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$' => {
+                    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+                },
+                _ => false,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[allow(dead_code)]
+    struct Task {
+        name: String,
+        tags: Vec<Option<String>>,
+        subtasks: Vec<Task>,
+    }
+
+    ts_interface!(Task { name: String, tags: Vec<Option<String>>, subtasks: Vec<Task> });
+
+    #[test]
+    fn test_types() {
+        assert_eq!("number", u8::ts_type());
+        assert_eq!("string | null", <Option<&str>>::ts_type());
+        assert_eq!("(number | null)[]", <Vec<Option<f64>>>::ts_type());
+        assert_eq!("[boolean, string]", <(bool, String)>::ts_type());
+        assert_eq!("{ [key: string]: null }", <HashMap<String, ()>>::ts_type());
+    }
+
+    #[test]
+    fn test_interface() {
+        let mut declarations = Declarations::default();
+
+        Task::ts_declare(&mut declarations);
+
+        assert_eq!(
+            "export interface Task {\n  name: string;\n  tags: (string | null)[];\n  subtasks: Task[];\n}",
+            declarations.declarations["Task"],
+        );
+    }
+
+    #[test]
+    fn test_bindings() {
+        let bindings = Bindings::new(vec![
+            ("addTask", Some(signature::<Task, usize> as Signature)),
+            ("init", Some(signature::<(), ()> as Signature)),
+            ("app.exit", None),
+            ("delete", None),
+            ("call", None),
+        ]);
+        let declarations = bindings.declarations();
+        let client = bindings.client();
+
+        assert!(declarations.contains("export interface Task {"));
+        assert!(declarations.contains("  \"addTask\": { args: Task; result: number };\n"));
+        assert!(declarations.contains("  \"app.exit\": { args: unknown; result: unknown };\n"));
+        assert!(declarations.contains("export declare function addTask(args: Task): Promise<number>;\n"));
+        assert!(declarations.contains("export declare function init(args?: null): Promise<null>;\n"));
+        assert!(!declarations.contains("function app.exit"));
+        assert!(client.contains("export function addTask(args) {\n  return call(\"addTask\", args);\n}\n"));
+        assert!(!client.contains("function app.exit"));
+        assert!(declarations.contains("  \"delete\": { args: unknown; result: unknown };\n"));
+        assert!(!client.contains("function delete"));
+        assert_eq!(1, client.matches("export function call(").count());
+    }
+}
@@ -0,0 +1,8 @@
+window.addEventListener('load', function() {
+  // Served like from a web server, so that fetch works too
+  fetch('/style.css').then(function() {
+    external.invoke(getComputedStyle(document.body).color);
+  }, function(e) {
+    external.invoke(String(e));
+  });
+});
@@ -1,8 +1,25 @@
+use std::cell::{
+    Cell,
+    RefCell,
+};
+use std::sync::mpsc;
 use std::thread;
+use std::time::{
+    Duration,
+    Instant,
+};
 use webview::*;
 
 const TERMINATE_HTML: &str = include_str!("fixtures/invoke_terminate.html");
 const INJECT_CSS_HTML: &str = include_str!("fixtures/inject_css.html");
+const RPC_CALL_HTML: &str = include_str!("fixtures/rpc_call.html");
+const EMIT_HTML: &str = include_str!("fixtures/emit.html");
+const ERRORS_HTML: &str = include_str!("fixtures/errors.html");
+const BYTES_HTML: &str = include_str!("fixtures/bytes.html");
+const CHANNEL_HTML: &str = include_str!("fixtures/channel.html");
+const CANCEL_HTML: &str = include_str!("fixtures/cancel.html");
+const ALLOWLIST_HTML: &str = include_str!("fixtures/allowlist.html");
+const PROTOCOL_HTML: &str = include_str!("fixtures/protocol.html");
 
 
 #[test]
@@ -92,6 +109,223 @@ fn test_inject_css() {
     thread.join().unwrap();
 }
 
+#[test]
+fn test_rpc_call() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html(RPC_CALL_HTML))
+        .user_data(())
+        .invoke_handler(call_handler(|view, request| {
+            match request.name() {
+                "add" => {
+                    let (a, b): (i32, i32) = request.args()?;
+                    Ok(a + b)
+                },
+                "done" => {
+                    assert_eq!(3, request.args::<i32>()?);
+                    view.terminate();
+                    Ok(0)
+                },
+                _ => panic!("Received unexpected call {}", request.name())
+            }
+        }))
+        .build()
+        .unwrap();
+    let handle = view.handle();
+
+    // Spawn thread which dispatch; dispatch calls eval which
+    // triggers `rust.call('add')`, whose result is passed back
+    // through `rust.call('done')` which terminates the app
+    let thread = thread::spawn(move || {
+        handle.dispatch(|view| {
+            view.eval("test_call()")
+        }).unwrap();
+    });
+
+    view.run().unwrap();
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_command() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html(RPC_CALL_HTML))
+        .user_data(())
+        .command("add", |_, (a, b): (i32, i32)| Ok(a + b))
+        .command("done", |view, sum: i32| {
+            assert_eq!(3, sum);
+            view.terminate();
+            Ok(())
+        })
+        .build()
+        .unwrap();
+    let handle = view.handle();
+
+    let thread = thread::spawn(move || {
+        handle.dispatch(|view| {
+            view.eval("test_call()")
+        }).unwrap();
+    });
+
+    view.run().unwrap();
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_unknown_command() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html(RPC_CALL_HTML))
+        .user_data(())
+        .command("done", |view, _: ()| {
+            view.terminate();
+            Ok(())
+        })
+        .build()
+        .unwrap();
+    let handle = view.handle();
+
+    // `add` was never registered, so the call errors out of `run()`
+    let thread = thread::spawn(move || {
+        handle.dispatch(|view| {
+            view.eval("test_call()")
+        }).unwrap();
+    });
+
+    match view.run() {
+        Err(Error::UnknownCommand(command)) => assert_eq!("add", command),
+        r => panic!("Unexpected result {:?}", r),
+    }
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_emit() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html(EMIT_HTML))
+        .user_data(())
+        .command("pong", |view, payload: Vec<String>| {
+            assert_eq!(vec!["</script>", "'\"\\"], payload);
+            view.terminate();
+            Ok(())
+        })
+        .build()
+        .unwrap();
+    let handle = view.handle();
+
+    // Emitted `ping` is echoed back by the listener in `emit.html`
+    let thread = thread::spawn(move || {
+        handle.emit("ping", ["</script>", "'\"\\"]).unwrap();
+    });
+
+    view.run().unwrap();
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_forward_errors() {
+    for &(function, name, message) in &[
+        ("test_reject()", "Custom", "Error: boom"),
+        ("test_error_event()", "Json", ""),
+        // Events named `error` emitted from Rust are not turned into errors
+        ("test_user_error_event()", "Object", "plain"),
+        ("test_panic()", "Panic", "Handler panicked: explode"),
+    ] {
+        let view = WebViewBuilder::new()
+            .size(1, 1)
+            .content(Content::Html(ERRORS_HTML))
+            .user_data(())
+            .forward_errors(true)
+            .middleware(catch_panic())
+            .command("fail", |_, _: ()| -> WVResult { Err(Error::custom("boom")) })
+            .command("explode", |_, _: ()| -> WVResult { panic!("explode") })
+            .command("emit_error", |view, _: ()| view.emit("error", serde_json::json!({ "message": "plain" })))
+            .command("done", move |view, (actual_name, actual_message): (String, String)| {
+                assert_eq!(name, actual_name);
+                assert!(actual_message.starts_with(message), "{}", actual_message);
+                view.terminate();
+                Ok(())
+            })
+            .build()
+            .unwrap();
+        let handle = view.handle();
+
+        let thread = thread::spawn(move || {
+            handle.dispatch(move |view| {
+                view.eval(function)
+            }).unwrap();
+        });
+
+        view.run().unwrap();
+        thread.join().unwrap();
+    }
+}
+
+#[test]
+fn test_forward_errors_fatal() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html(ERRORS_HTML))
+        .user_data(())
+        .forward_errors(true)
+        .command("fatal", |_, _: ()| -> WVResult { Err(Error::fatal(Error::custom("boom"))) })
+        .build()
+        .unwrap();
+    let handle = view.handle();
+
+    let thread = thread::spawn(move || {
+        handle.dispatch(|view| {
+            view.eval("test_fatal()")
+        }).unwrap();
+    });
+
+    match view.run() {
+        Err(e) => assert!(e.is_fatal()),
+        r => panic!("Unexpected result {:?}", r),
+    }
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_middleware() {
+    let mut seen = Vec::new();
+
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html(RPC_CALL_HTML))
+        .user_data(())
+        .middleware(catch_panic())
+        .middleware(|view, message, next: Next<_>| {
+            seen.push(message.to_string());
+            next.run(view, message)
+        })
+        .middleware(|view, message, next: Next<_>| {
+            // Refuse `done` so that only the panic ends the test
+            match Request::parse(message)?.name() {
+                "done" => panic!("done"),
+                _ => next.run(view, message),
+            }
+        })
+        .command("add", |_, (a, b): (i32, i32)| Ok(a + b))
+        .build()
+        .unwrap();
+    let handle = view.handle();
+
+    let thread = thread::spawn(move || {
+        handle.dispatch(|view| {
+            view.eval("test_call()")
+        }).unwrap();
+    });
+
+    match view.run() {
+        Err(Error::Panic(message)) => assert_eq!("done", message),
+        r => panic!("Unexpected result {:?}", r),
+    }
+    thread.join().unwrap();
+    assert_eq!(2, seen.len());
+}
 
 #[test]
 fn test_window_size() {
@@ -102,35 +336,66 @@ fn test_window_size() {
         .size(width, height)
         .content(Content::Html(INJECT_CSS_HTML))
         .user_data(())
-        .invoke_handler(|view, arg| {
-            match arg {
-                w if w.contains("width") => {
-                    let actual_width: Vec<&str> = w.split(' ').collect();
-                    assert_eq!(width, actual_width[1].parse::<i32>().unwrap());
-                },
-                h if h.contains("height") => {
-                    let actual_width: Vec<&str> = h.split(' ').collect();
-                    assert_eq!(height, actual_width[1].parse::<i32>().unwrap());
-                },
-                _ => panic!("Unexpected argument {}", arg)
-            }
+        .invoke_handler(|_, _| Ok(()))
+        .build()
+        .unwrap();
+    let handle = view.handle();
+
+    // Spawn thread which evaluates the width & height in JS,
+    // which we compare to our expected values
+    let thread = thread::spawn(move || {
+        let size: (i32, i32) = handle.eval_sync("[window.innerWidth, window.innerHeight]").unwrap();
 
+        handle.dispatch(|view| {
             view.terminate();
             Ok(())
-        })
+        }).unwrap();
+
+        size
+    });
+
+    view.run().unwrap();
+    assert_eq!((width, height), thread.join().unwrap());
+}
+
+#[test]
+fn test_eval_sync() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html(INJECT_CSS_HTML))
+        .user_data(())
+        .invoke_handler(|_, _| Ok(()))
         .build()
         .unwrap();
     let handle = view.handle();
 
-    // Spawn thread which dispatch; dispatch calls
-    // invoke which provides the width & height from JS
-    // which we compare to our expected values
     let thread = thread::spawn(move || {
+        assert_eq!(4, handle.eval_sync::<i32>("Promise.resolve(2 + 2)").unwrap());
+        assert_eq!("app", handle.eval_sync::<String>("document.querySelector('div').id // comment").unwrap());
+
+        match handle.eval_sync::<i32>("undefined_function()") {
+            Err(Error::JsException { message, .. }) => assert!(message.contains("undefined_function")),
+            r => panic!("Unexpected result {:?}", r),
+        }
+
+        match handle.eval_sync::<i32>("'not a number'") {
+            Err(Error::Json(_)) => (),
+            r => panic!("Unexpected result {:?}", r),
+        }
+
+        match handle.eval_sync_timeout::<i32>("new Promise(function() {})", Duration::from_millis(200)) {
+            Err(Error::Timeout) => (),
+            r => panic!("Unexpected result {:?}", r),
+        }
+
+        // The promise is abandoned as the page reloads
+        match handle.eval_sync::<i32>("new Promise(function() { location.reload(); })") {
+            Err(Error::Unloaded) => (),
+            r => panic!("Unexpected result {:?}", r),
+        }
+
         handle.dispatch(|view| {
-            view.eval("external.invoke(`width ${window.innerWidth}`)")?;
-            view.eval("external.invoke(`height ${window.innerHeight}`)")?;
             view.terminate();
-
             Ok(())
         }).unwrap();
     });
@@ -140,27 +405,746 @@ fn test_window_size() {
 }
 
 #[test]
-fn test_misc() {
+fn test_bytes() {
     let view = WebViewBuilder::new()
         .size(1, 1)
-        .content(Content::Url("http://dummy.url"))
+        .content(Content::Html(BYTES_HTML))
         .user_data(())
-        .invoke_handler(|_, _| Ok(()))
+        .command("echo", |_, bytes: Bytes| {
+            assert_eq!(1024 * 1024, bytes.len());
+            assert_eq!(0, bytes[256]);
+            Ok(bytes)
+        })
+        .command("same", |_, value: serde_json::Value| Ok(value))
+        .command("done", |view, equal: bool| {
+            assert!(equal);
+            view.terminate();
+            Ok(())
+        })
         .build()
         .unwrap();
     let handle = view.handle();
 
-    // testing these is difficult, so just call and assert nothing explodes
     let thread = thread::spawn(move || {
         handle.dispatch(|view| {
-            assert!(view.set_title("Some title").is_ok());
-            view.set_color((0, 0, 0, 100));
+            view.eval("test_bytes()")
+        }).unwrap();
+    });
+
+    view.run().unwrap();
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_channel() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html(CHANNEL_HTML))
+        .user_data(())
+        .command("done", |view, result: serde_json::Value| {
+            assert_eq!(serde_json::json!({ "received": [1, 2, 3, "a"], "error": "UnknownCommand" }), result);
+            view.terminate();
+            Ok(())
+        })
+        .build()
+        .unwrap();
+    let handle = view.handle();
+
+    let thread = thread::spawn(move || {
+        let mut numbers = handle.channel("numbers");
+        let mut failing = handle.channel("failing");
+
+        for i in 1..4 {
+            numbers.send(i).unwrap();
+        }
+
+        failing.send("a").unwrap();
+        failing.error(Error::UnknownCommand("a".to_string())).unwrap();
+        numbers.close().unwrap();
+    });
+
+    view.run().unwrap();
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_deferred_error() {
+    let (sender, receiver) = mpsc::channel();
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html("<script>rust.call('fail').catch(function () { rust.call('done'); });</script>"))
+        .user_data(())
+        .forward_errors(true)
+        .deferred_command("fail", move |_, _: (), call| {
+            sender.send(call.token()).unwrap();
+            Err(Error::custom("failed"))
+        })
+        .command("done", |view, _: ()| {
+            view.terminate();
+            Ok(())
+        })
+        .build()
+        .unwrap();
+
+    view.run().unwrap();
+
+    // The call was rejected with the error, and is over for workers too
+    assert!(receiver.recv().unwrap().is_cancelled());
+}
+
+#[test]
+fn test_cancel() {
+    let (sender, receiver) = mpsc::channel();
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html(CANCEL_HTML))
+        .user_data(())
+        .deferred_command("slow", move |_, _: (), call| {
+            let sender = sender.clone();
+
+            thread::spawn(move || {
+                let token = call.token();
+                let start = Instant::now();
+
+                while !token.is_cancelled() && start.elapsed() < Duration::from_secs(5) {
+                    thread::sleep(Duration::from_millis(10));
+                }
+
+                sender.send(token.is_cancelled()).unwrap();
+                call.resolve(())
+            });
+
+            Ok(())
+        })
+        .command("done", |view, outcome: String| {
+            assert_eq!("AbortError", outcome);
             view.terminate();
+            Ok(())
+        })
+        .build()
+        .unwrap();
+    let handle = view.handle();
+
+    let thread = thread::spawn(move || {
+        handle.dispatch(|view| {
+            view.eval("test_cancel()")
+        }).unwrap();
+    });
+
+    view.run().unwrap();
+    thread.join().unwrap();
 
+    assert!(receiver.recv_timeout(Duration::from_secs(5)).unwrap());
+}
+
+#[test]
+fn test_allowlist() {
+    let (sender, receiver) = mpsc::channel();
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html(ALLOWLIST_HTML))
+        .user_data(())
+        .command("secret", |_, ()| -> WVResult {
+            panic!("secret should not be callable");
+        })
+        .command("done", |view, outcome: String| {
+            assert_eq!("Forbidden", outcome);
+            view.terminate();
             Ok(())
+        })
+        .allow("data:*", &["done"])
+        .allow("https://example.com/*", &["secret"])
+        .on_violation(move |violation| {
+            sender.send(violation.command.clone()).unwrap();
+        })
+        .build()
+        .unwrap();
+    let handle = view.handle();
+
+    let thread = thread::spawn(move || {
+        handle.dispatch(|view| {
+            view.eval("test_allowlist()")
         }).unwrap();
     });
 
     view.run().unwrap();
     thread.join().unwrap();
+
+    assert_eq!(Some("secret".to_string()), receiver.recv().unwrap());
+}
+
+#[test]
+fn test_batch() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html("<script>var log = [];</script>"))
+        .user_data(())
+        .invoke_handler(|_, _| Ok(()))
+        .build()
+        .unwrap();
+    let handle = view.handle();
+
+    let thread = thread::spawn(move || {
+        for i in 0..100 {
+            handle.eval_batched(&format!("log.push({})", i)).unwrap();
+            handle.eval_keyed("latest", &format!("window.latest = {}", i)).unwrap();
+        }
+
+        handle.eval_batched("throw new Error('ignored')").unwrap();
+        handle.eval_batched("this is not JavaScript").unwrap();
+        handle.eval_batched("let scoped = 'global'").unwrap();
+        handle.eval_batched("log.push('after')").unwrap();
+
+        let (log, latest, scoped): (Vec<serde_json::Value>, i32, String) = handle
+            .eval_sync("[log, window.latest, scoped]")
+            .unwrap();
+
+        assert_eq!(101, log.len());
+        assert_eq!("after", log[100]);
+        assert_eq!(99, latest);
+        assert_eq!("global", scoped);
+
+        handle.dispatch(|view| {
+            view.terminate();
+            Ok(())
+        }).unwrap();
+    });
+
+    view.run().unwrap();
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_batch_drop_oldest() {
+    let (sender, receiver) = mpsc::channel();
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html("<script>external.invoke('ready')</script>"))
+        .user_data(())
+        .dispatch_queue(2, Overflow::DropOldest)
+        .invoke_handler(move |view, _| {
+            let handle = view.handle();
+            let sender = sender.clone();
+
+            thread::spawn(move || {
+                handle.eval_keyed("latest", "window.latest = 1").unwrap();
+
+                // Fills the queue, dropping all but the closure evaluating the batch
+                for _ in 0..10 {
+                    handle.dispatch(|_| Ok(())).unwrap();
+                }
+
+                handle.eval_keyed("latest", "window.latest = 2").unwrap();
+                sender.send(handle.eval_sync::<i32>("window.latest")).unwrap();
+                handle.dispatch(|view| {
+                    view.terminate();
+                    Ok(())
+                }).unwrap();
+            });
+
+            Ok(())
+        })
+        .build()
+        .unwrap();
+
+    view.run().unwrap();
+
+    assert_eq!(2, receiver.recv().unwrap().unwrap());
+}
+
+#[test]
+fn test_dispatch_queue_full() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Url("http://dummy.url"))
+        .user_data(())
+        .invoke_handler(|_, _| Ok(()))
+        .dispatch_queue(1, Overflow::Error)
+        .build()
+        .unwrap();
+    let handle = view.handle();
+
+    // The queue is not drained until the WebView runs
+    thread::spawn(move || {
+        handle.dispatch(|view| {
+            view.terminate();
+            Ok(())
+        }).unwrap();
+
+        match handle.dispatch(|_| Ok(())) {
+            Err(Error::QueueFull) => (),
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }).join().unwrap();
+
+    view.run().unwrap();
+}
+
+#[test]
+fn test_max_message_size() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html("<html></html>"))
+        .user_data(())
+        .invoke_handler(|_, _| -> WVResult {
+            panic!("the message should have been dropped");
+        })
+        .max_message_size(10)
+        .build()
+        .unwrap();
+    let handle = view.handle();
+
+    let thread = thread::spawn(move || {
+        handle.dispatch(|view| {
+            view.eval("external.invoke('x'.repeat(100))")
+        }).unwrap();
+    });
+
+    match view.run() {
+        Err(Error::MessageTooLarge { size: 100, limit: 10 }) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+
+    thread.join().unwrap();
+
+    // Calls are rejected rather than left pending
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html(
+            "<script>
+                rust.call('echo', 'x'.repeat(1000)).catch(function (e) { rust.call('done', e.name); });
+            </script>",
+        ))
+        .user_data(())
+        .command("echo", |_, _: String| -> WVResult {
+            panic!("the call should have been rejected");
+        })
+        .command("done", |view, name: String| {
+            assert_eq!("MessageTooLarge", name);
+            view.terminate();
+            Ok(())
+        })
+        .max_message_size(100)
+        .build()
+        .unwrap();
+
+    view.run().unwrap();
+}
+
+#[test]
+fn test_misc() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Url("http://dummy.url"))
+        .user_data(())
+        .invoke_handler(|_, _| Ok(()))
+        .build()
+        .unwrap();
+    let handle = view.handle();
+
+    // testing these is difficult, so just call and assert nothing explodes
+    let thread = thread::spawn(move || {
+        handle.dispatch(|view| {
+            assert!(view.set_title("Some title").is_ok());
+            view.set_color((0, 0, 0, 100));
+            view.terminate();
+
+            Ok(())
+        }).unwrap();
+    });
+
+    view.run().unwrap();
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_dir() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/site")))
+        .user_data(())
+        .invoke_handler(|view, arg| {
+            // The script and stylesheet are loaded from the root of the directory
+            assert_eq!("rgb(1, 2, 3)", arg);
+            view.terminate();
+            Ok(())
+        })
+        .build()
+        .unwrap();
+
+    view.run().unwrap();
+
+    let result = WebViewBuilder::new()
+        .content(Content::File(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/missing.html")))
+        .user_data(())
+        .invoke_handler(|_, _| Ok(()))
+        .build();
+
+    match result {
+        Err(Error::Io(_)) => (),
+        Err(e) => panic!("Unexpected error {}", e),
+        Ok(_) => panic!("Built a WebView for a missing file"),
+    }
+}
+
+#[test]
+fn test_assets() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::assets(assets!("fixtures/site" => ["index.html", "app.js", "style.css"])))
+        .user_data(())
+        .invoke_handler(|view, arg| {
+            // The script and stylesheet are served from the binary with their MIME types
+            assert_eq!("rgb(1, 2, 3)", arg);
+            view.terminate();
+            Ok(())
+        })
+        .build()
+        .unwrap();
+
+    view.run().unwrap();
+}
+
+#[test]
+fn test_protocol() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Url("media://localhost/"))
+        .user_data(())
+        .protocol("media", |request| match request.path().as_str() {
+            "/" => Response::new(PROTOCOL_HTML.as_bytes()).mime("text/html"),
+            "/media/clip.bin" => Response::new(b"0123456789".to_vec()).mime("text/plain"),
+            _ => Response::not_found(),
+        })
+        .invoke_handler(|view, arg| {
+            assert_eq!("206 text/plain 234", arg);
+            view.terminate();
+            Ok(())
+        })
+        .build()
+        .unwrap();
+
+    view.run().unwrap();
+}
+
+#[cfg(feature = "server")]
+#[test]
+fn test_serve() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html(r#"<script>
+            fetch('/api/ping').then(function(response) { return response.text(); }).then(function(text) {
+                external.invoke(location.protocol + ' ' + text);
+            });
+        </script>"#))
+        .user_data(())
+        .serve_with(|request| match request.path().as_str() {
+            "/api/ping" => Response::new(&b"pong"[..]),
+            _ => Response::not_found(),
+        })
+        .invoke_handler(|view, arg| {
+            // Requests after the first page authenticate with the cookie
+            assert_eq!("http: pong", arg);
+            view.terminate();
+            Ok(())
+        })
+        .build()
+        .unwrap();
+
+    view.run().unwrap();
+}
+
+#[test]
+fn test_navigate() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html("<script>external.invoke('login')</script>"))
+        .user_data(())
+        .invoke_handler(|view, arg| {
+            match arg {
+                "login" => view.navigate(Content::Html("<script>external.invoke('main')</script>")),
+                "main" => {
+                    assert!(view.current_url().contains("main"));
+                    view.terminate();
+                    Ok(())
+                },
+                _ => panic!("Received unexpected arg {}", arg),
+            }
+        })
+        .build()
+        .unwrap();
+
+    view.run().unwrap();
+}
+
+#[test]
+fn test_navigate_keeps_protocol() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html("<script>external.invoke('loaded')</script>"))
+        .user_data(())
+        .protocol("app", |_| Response::new(b"mine".to_vec()))
+        .invoke_handler(|view, _| {
+            // The handler set above is not replaced by the one of the assets
+            match view.navigate(Content::assets(assets!("fixtures/site" => ["index.html"]))) {
+                Err(Error::Unsupported(_)) => (),
+                r => panic!("Unexpected result {:?}", r),
+            }
+
+            view.terminate();
+            Ok(())
+        })
+        .build()
+        .unwrap();
+
+    view.run().unwrap();
+}
+
+#[test]
+fn test_init_script() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html("<script>external.invoke(String(window.greeting))</script>"))
+        .user_data((None, false))
+        .init_script("window.greeting = 'hello';")
+        .invoke_handler(|view, arg| {
+            // Scripts run before those of the page, on every load until they are removed
+            let lock = view.user_data();
+            let mut state = lock.write().unwrap();
+
+            match (arg, *state) {
+                ("hello", (None, false)) => {
+                    state.0 = Some(view.add_init_script("window.greeting += ' again';")?);
+                    view.reload()
+                },
+                ("hello again", (Some(id), false)) => {
+                    view.remove_init_script(id)?;
+                    state.1 = true;
+                    view.reload()
+                },
+                ("hello", (Some(_), true)) => {
+                    view.terminate();
+                    Ok(())
+                },
+                _ => panic!("Received unexpected arg {}", arg),
+            }
+        })
+        .build()
+        .unwrap();
+
+    view.run().unwrap();
+}
+
+#[test]
+fn test_load_events() {
+    let dom_content_loaded = Cell::new(0);
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html("<p id='status'>loading</p>"))
+        .user_data(Vec::new())
+        .on_load(|view, event| {
+            view.user_data().write().unwrap().push(event.clone());
+
+            if let LoadEvent::Finished { .. } = event {
+                view.terminate();
+            }
+
+            Ok(())
+        })
+        .on_dom_content_loaded(|view, url| {
+            assert!(url.starts_with("data:"));
+            dom_content_loaded.set(dom_content_loaded.get() + 1);
+            // The DOM exists without waiting on another thread, and events dispatched by the page
+            // are not passed on
+            view.eval(
+                "document.dispatchEvent(new Event('DOMContentLoaded'));
+                external.invoke(document.getElementById('status').textContent)"
+            )
+        })
+        .invoke_handler(|_, arg| {
+            assert_eq!("loading", arg);
+            Ok(())
+        })
+        .build()
+        .unwrap();
+    let events = view.user_data();
+
+    view.run().unwrap();
+
+    let events = events.read().unwrap();
+
+    match events.last() {
+        Some(LoadEvent::Finished { url }) => assert!(url.starts_with("data:")),
+        event => panic!("Unexpected last event {:?}", event),
+    }
+
+    assert_eq!(1, dom_content_loaded.get());
+}
+
+#[test]
+fn test_navigation_handler() {
+    let seen = RefCell::new(Vec::new());
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html(
+            "<iframe src='external://localhost/frame'></iframe>
+            <a id='link' href='external://localhost/page'>link</a>
+            <script>
+                document.getElementById('link').click();
+                setTimeout(function () { external.invoke(location.href.slice(0, 5)); }, 200);
+            </script>",
+        ))
+        .user_data(())
+        .protocol("external", |_| Response::new(b"<p>external</p>".to_vec()).mime("text/html"))
+        .on_navigation(|url, kind| {
+            seen.borrow_mut().push((url.to_string(), kind));
+
+            if url.starts_with("data:") {
+                Decision::Allow
+            } else {
+                Decision::Block
+            }
+        })
+        .invoke_handler(|view, arg| {
+            // Still on the page after the link was blocked
+            assert_eq!("data:", arg);
+            view.terminate();
+            Ok(())
+        })
+        .build()
+        .unwrap();
+
+    view.run().unwrap();
+
+    let seen = seen.borrow();
+
+    assert!(seen.contains(&("external://localhost/page".to_string(), NavigationKind::LinkClicked)));
+    // The frame within the page is left alone
+    assert!(!seen.iter().any(|(url, _)| url.ends_with("/frame")));
+}
+
+#[test]
+fn test_live_reload() {
+    let dir = std::env::temp_dir().join("webview live reload test");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("style.css"), "body { color: rgb(1, 2, 3); }").unwrap();
+    std::fs::write(
+        dir.join("index.html"),
+        "<link rel='stylesheet' href='style.css'>
+        <script>
+            var page = String(Math.random()), last;
+            setInterval(function () {
+                var color = getComputedStyle(document.body).color;
+                if (color !== last) {
+                    last = color;
+                    external.invoke(JSON.stringify([page, color]));
+                }
+            }, 50);
+        </script>",
+    )
+    .unwrap();
+
+    let css = dir.join("style.css");
+    let (sender, receiver) = mpsc::channel();
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Dir(dir.to_str().unwrap()))
+        .debug(true)
+        .user_data(None)
+        .invoke_handler(move |view, arg| {
+            let (page, color): (String, String) = serde_json::from_str(arg).unwrap();
+            let data = view.user_data();
+            let mut first = data.write().unwrap();
+
+            match (first.as_ref(), color.as_str()) {
+                (None, "rgb(1, 2, 3)") => {
+                    *first = Some(page);
+                    std::fs::write(&css, "body { color: rgb(4, 5, 6); }").unwrap();
+                },
+                (Some(first), "rgb(4, 5, 6)") => {
+                    // The stylesheet was swapped without reloading the page
+                    assert_eq!(first, &page);
+                    sender.send(()).unwrap();
+                    view.terminate();
+                },
+                _ => panic!("Received unexpected arg {}", arg),
+            }
+
+            Ok(())
+        })
+        .build()
+        .unwrap();
+    let handle = view.handle();
+
+    // Give up if the change is never noticed
+    thread::spawn(move || {
+        thread::sleep(Duration::from_secs(10));
+        let _ = handle.dispatch(|view| {
+            view.terminate();
+            Ok(())
+        });
+    });
+
+    view.run().unwrap();
+
+    assert!(receiver.try_recv().is_ok(), "The stylesheet was not swapped in time");
+}
+
+#[test]
+fn test_csp() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html(
+            "<!DOCTYPE html>
+            <script>
+                var blocked;
+                try { eval('1'); blocked = false; } catch (e) { blocked = true; }
+                external.invoke(JSON.stringify([blocked, typeof window.rust]));
+            </script>",
+        ))
+        .csp("default-src 'none'; script-src 'unsafe-inline'")
+        .user_data(())
+        .invoke_handler(|view, arg| {
+            // The bridge still runs while eval is disabled in the page
+            assert_eq!(r#"[true,"object"]"#, arg);
+            view.terminate();
+            Ok(())
+        })
+        .build()
+        .unwrap();
+
+    view.run().unwrap();
+
+    let result = WebViewBuilder::new()
+        .content(Content::Dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/site")))
+        .csp("default-src 'self'")
+        .user_data(())
+        .invoke_handler(|_, _| Ok(()))
+        .build();
+
+    match result {
+        Err(Error::Unsupported(_)) => (),
+        Err(e) => panic!("Unexpected error {}", e),
+        Ok(_) => panic!("Built a WebView for a directory without a policy"),
+    }
+}
+
+#[test]
+fn test_settings() {
+    let view = WebViewBuilder::new()
+        .size(1, 1)
+        .content(Content::Html(
+            "<script>external.invoke(JSON.stringify([navigator.userAgent, !!window.localStorage]))</script>",
+        ))
+        .settings(WebSettings::new().user_agent("WebViewTest/1.0").local_storage(false))
+        .user_data(())
+        .invoke_handler(|view, arg| {
+            assert_eq!(r#"["WebViewTest/1.0",false]"#, arg);
+            view.terminate();
+            Ok(())
+        })
+        .build()
+        .unwrap();
+
+    view.run().unwrap();
 }
@@ -16,6 +16,11 @@ use crate::webview_bind_win::webview_priv;
 
 pub type InvokeFn = unsafe extern "C" fn(WebViewFFI: *mut WebViewFFI, arg: *const c_char);
 pub type DispatchFn = unsafe extern "C" fn(WebViewFFI: *mut WebViewFFI, arg: *mut c_void);
+pub type UriSchemeFn = unsafe extern "C" fn(webview: *mut WebViewFFI, request: *mut c_void, uri: *const c_char);
+pub type HeaderFn = unsafe extern "C" fn(name: *const c_char, value: *const c_char, arg: *mut c_void);
+pub type LoadFn = unsafe extern "C" fn(webview: *mut WebViewFFI, event: c_int, uri: *const c_char, error: *const c_char);
+pub type NavigationFn = unsafe extern "C" fn(webview: *mut WebViewFFI, uri: *const c_char, kind: c_int) -> c_int;
+pub type BodyFn = unsafe extern "C" fn(data: *const c_char, len: usize, arg: *mut c_void);
 
 #[repr(C)]
 pub struct WebViewFFI {
@@ -59,4 +64,22 @@ extern {
 	pub fn webview_set_fullscreen(webview: *mut WebViewFFI, fullscreen: c_int);
 	pub fn webview_set_color(webview: *mut WebViewFFI, red: u8, green: u8, blue: u8, alpha: u8);
 	pub fn webview_dialog(webview: *mut WebViewFFI, dialog_type: DialogType, flags: DialogFlags, title: *const c_char, arg: *const c_char, result: *mut c_char, result_size: usize);
+	pub fn webview_add_user_script(webview: *mut WebViewFFI, js: *const c_char) -> c_int;
+	pub fn webview_remove_user_scripts(webview: *mut WebViewFFI) -> c_int;
+	pub fn webview_get_uri(webview: *mut WebViewFFI) -> *const c_char;
+	pub fn webview_register_uri_scheme(scheme: *const c_char, cors: c_int) -> c_int;
+	pub fn webview_set_uri_scheme_handler(webview: *mut WebViewFFI, f: Option<UriSchemeFn>);
+	pub fn webview_uri_scheme_respond(request: *mut c_void, status: c_int, names: *const *const c_char, values: *const *const c_char, count: usize, data: *const c_char, len: usize, mime: *const c_char);
+	pub fn webview_uri_scheme_method(request: *mut c_void) -> *const c_char;
+	pub fn webview_uri_scheme_headers(request: *mut c_void, f: Option<HeaderFn>, arg: *mut c_void);
+	pub fn webview_uri_scheme_body(request: *mut c_void, f: Option<BodyFn>, arg: *mut c_void);
+	pub fn webview_uri_scheme_fail(request: *mut c_void, message: *const c_char);
+	pub fn webview_navigate(webview: *mut WebViewFFI, url: *const c_char) -> c_int;
+	pub fn webview_reload(webview: *mut WebViewFFI) -> c_int;
+	pub fn webview_go_back(webview: *mut WebViewFFI) -> c_int;
+	pub fn webview_go_forward(webview: *mut WebViewFFI) -> c_int;
+	pub fn webview_stop_loading(webview: *mut WebViewFFI) -> c_int;
+	pub fn webview_set_load_handler(webview: *mut WebViewFFI, f: Option<LoadFn>) -> c_int;
+	pub fn webview_set_navigation_handler(webview: *mut WebViewFFI, f: Option<NavigationFn>) -> c_int;
+	pub fn webview_apply_settings(webview: *mut WebViewFFI, user_agent: *const c_char, javascript: c_int, local_storage: c_int, webgl: c_int, autoplay: c_int, default_font_size: c_int, monospace_font_size: c_int, file_access: c_int) -> c_int;
 }
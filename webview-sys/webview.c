@@ -1,2 +1,564 @@
 #define WEBVIEW_IMPLEMENTATION
 #include "webview.h"
+
+/*
+ * Extensions to the upstream webview API used by the Rust bindings.
+ */
+
+/* Adds a script which runs at document start on every page load. Returns -1
+ * where the platform has no such facility. */
+WEBVIEW_API int webview_add_user_script(struct webview *w, const char *js) {
+#if defined(WEBVIEW_GTK)
+  WebKitUserContentManager *manager =
+      webkit_web_view_get_user_content_manager(WEBKIT_WEB_VIEW(w->priv.webview));
+  WebKitUserScript *script = webkit_user_script_new(
+      js, WEBKIT_USER_CONTENT_INJECT_TOP_FRAME,
+      WEBKIT_USER_SCRIPT_INJECT_AT_DOCUMENT_START, NULL, NULL);
+
+  webkit_user_content_manager_add_script(manager, script);
+  webkit_user_script_unref(script);
+  return 0;
+#else
+  (void)w;
+  (void)js;
+  return -1;
+#endif
+}
+
+/* Removes the scripts added with webview_add_user_script, from the next page
+ * load on. Returns -1 where scripts cannot be removed. */
+WEBVIEW_API int webview_remove_user_scripts(struct webview *w) {
+#if defined(WEBVIEW_GTK)
+  webkit_user_content_manager_remove_all_scripts(
+      webkit_web_view_get_user_content_manager(WEBKIT_WEB_VIEW(w->priv.webview)));
+  return 0;
+#else
+  (void)w;
+  return -1;
+#endif
+}
+
+/* Returns the URI of the current page, owned by the webview, or NULL where the
+ * platform does not expose it. */
+WEBVIEW_API const char *webview_get_uri(struct webview *w) {
+#if defined(WEBVIEW_GTK)
+  return webkit_web_view_get_uri(WEBKIT_WEB_VIEW(w->priv.webview));
+#else
+  (void)w;
+  return NULL;
+#endif
+}
+
+typedef void (*webview_uri_scheme_fn)(struct webview *w, void *request,
+                                      const char *uri);
+
+/* Fails a request made to a custom URI scheme with message. */
+WEBVIEW_API void webview_uri_scheme_fail(void *request, const char *message) {
+#if defined(WEBVIEW_GTK)
+  GError *error = g_error_new_literal(G_IO_ERROR, G_IO_ERROR_NOT_FOUND, message);
+
+  webkit_uri_scheme_request_finish_error((WebKitURISchemeRequest *)request,
+                                         error);
+  g_error_free(error);
+#else
+  (void)request;
+  (void)message;
+#endif
+}
+
+/* Finishes a request made to a custom URI scheme with a copy of data. The
+ * status and headers are only sent with WebKitGTK 2.36 and later, before which
+ * error statuses fail the request instead. */
+WEBVIEW_API void webview_uri_scheme_respond(void *request, int status,
+                                            const char *const *names,
+                                            const char *const *values,
+                                            size_t count, const char *data,
+                                            size_t len, const char *mime) {
+#if defined(WEBVIEW_GTK)
+  GInputStream *stream =
+      g_memory_input_stream_new_from_data(g_memdup(data, len), len, g_free);
+#if WEBKIT_CHECK_VERSION(2, 36, 0)
+  WebKitURISchemeResponse *response =
+      webkit_uri_scheme_response_new(stream, len);
+  SoupMessageHeaders *headers =
+      soup_message_headers_new(SOUP_MESSAGE_HEADERS_RESPONSE);
+  size_t i;
+
+  for (i = 0; i < count; i++) {
+    soup_message_headers_append(headers, names[i], values[i]);
+  }
+  webkit_uri_scheme_response_set_status(response, status, NULL);
+  webkit_uri_scheme_response_set_content_type(response, mime);
+  webkit_uri_scheme_response_set_http_headers(response, headers);
+  webkit_uri_scheme_request_finish_with_response(
+      (WebKitURISchemeRequest *)request, response);
+  g_object_unref(response);
+#else
+  (void)names;
+  (void)values;
+  (void)count;
+  if (status >= 400) {
+    webview_uri_scheme_fail(request, "Request failed");
+  } else {
+    webkit_uri_scheme_request_finish((WebKitURISchemeRequest *)request,
+                                     stream, len, mime);
+  }
+#endif
+  g_object_unref(stream);
+#else
+  (void)request;
+  (void)status;
+  (void)names;
+  (void)values;
+  (void)count;
+  (void)data;
+  (void)len;
+  (void)mime;
+#endif
+}
+
+/* Returns the HTTP method of a request made to a custom URI scheme, which is
+ * always GET before WebKitGTK 2.36. */
+WEBVIEW_API const char *webview_uri_scheme_method(void *request) {
+#if defined(WEBVIEW_GTK) && WEBKIT_CHECK_VERSION(2, 36, 0)
+  const char *method = webkit_uri_scheme_request_get_http_method(
+      (WebKitURISchemeRequest *)request);
+
+  return method != NULL ? method : "GET";
+#else
+  (void)request;
+  return "GET";
+#endif
+}
+
+typedef void (*webview_header_fn)(const char *name, const char *value,
+                                  void *arg);
+
+/* Calls fn with each header of a request made to a custom URI scheme. Headers
+ * are only available with WebKitGTK 2.36 and later. */
+WEBVIEW_API void webview_uri_scheme_headers(void *request, webview_header_fn fn,
+                                            void *arg) {
+#if defined(WEBVIEW_GTK) && WEBKIT_CHECK_VERSION(2, 36, 0)
+  SoupMessageHeaders *headers = webkit_uri_scheme_request_get_http_headers(
+      (WebKitURISchemeRequest *)request);
+
+  if (headers != NULL) {
+    soup_message_headers_foreach(headers, (SoupMessageHeadersForeachFunc)fn,
+                                 arg);
+  }
+#else
+  (void)request;
+  (void)fn;
+  (void)arg;
+#endif
+}
+
+typedef void (*webview_body_fn)(const char *data, size_t len, void *arg);
+
+/* Calls fn with each piece of the body of a request made to a custom URI
+ * scheme. Bodies are only available with WebKitGTK 2.40 and later. */
+WEBVIEW_API void webview_uri_scheme_body(void *request, webview_body_fn fn,
+                                         void *arg) {
+#if defined(WEBVIEW_GTK) && WEBKIT_CHECK_VERSION(2, 40, 0)
+  GInputStream *body = webkit_uri_scheme_request_get_http_body(
+      (WebKitURISchemeRequest *)request);
+  char buffer[4096];
+  gssize len;
+
+  if (body == NULL) {
+    return;
+  }
+  while ((len = g_input_stream_read(body, buffer, sizeof(buffer), NULL,
+                                    NULL)) > 0) {
+    fn(buffer, (size_t)len, arg);
+  }
+  g_object_unref(body);
+#else
+  (void)request;
+  (void)fn;
+  (void)arg;
+#endif
+}
+
+#if defined(WEBVIEW_GTK)
+static void webview_uri_scheme_cb(WebKitURISchemeRequest *request,
+                                  gpointer arg) {
+  GObject *view = G_OBJECT(webkit_uri_scheme_request_get_web_view(request));
+  struct webview *w = g_object_get_data(view, "webview");
+  webview_uri_scheme_fn fn =
+      (webview_uri_scheme_fn)g_object_get_data(view, "webview-uri-scheme-fn");
+
+  (void)arg;
+  if (w == NULL || fn == NULL) {
+    webview_uri_scheme_fail(request, "No handler for this URI scheme");
+    return;
+  }
+  fn(w, request, webkit_uri_scheme_request_get_uri(request));
+}
+#endif
+
+/* Registers a custom URI scheme, whose requests are passed to the handler set
+ * on the webview making them. With cors, pages of other origins may request it
+ * subject to CORS, and module scripts may be loaded from it. Schemes must be
+ * registered before the webviews using them are initialized, and stay
+ * registered for the whole process. Returns -1 where the platform does not
+ * support custom schemes. */
+WEBVIEW_API int webview_register_uri_scheme(const char *scheme, int cors) {
+#if defined(WEBVIEW_GTK)
+  static GHashTable *registered = NULL;
+  WebKitWebContext *context = webkit_web_context_get_default();
+  WebKitSecurityManager *security =
+      webkit_web_context_get_security_manager(context);
+
+  if (registered == NULL) {
+    registered = g_hash_table_new_full(g_str_hash, g_str_equal, g_free, NULL);
+  }
+  if (!g_hash_table_contains(registered, scheme)) {
+    g_hash_table_add(registered, g_strdup(scheme));
+    webkit_web_context_register_uri_scheme(context, scheme,
+                                           webview_uri_scheme_cb, NULL, NULL);
+    webkit_security_manager_register_uri_scheme_as_secure(security, scheme);
+  }
+  if (cors) {
+    webkit_security_manager_register_uri_scheme_as_cors_enabled(security,
+                                                                scheme);
+  }
+  return 0;
+#else
+  (void)scheme;
+  (void)cors;
+  return -1;
+#endif
+}
+
+/* Sets the handler of requests made by the webview to custom URI schemes. As
+ * loading only starts once the event loop runs, it can be set right after
+ * webview_init. */
+WEBVIEW_API void webview_set_uri_scheme_handler(struct webview *w,
+                                                webview_uri_scheme_fn fn) {
+#if defined(WEBVIEW_GTK)
+  GObject *view = G_OBJECT(w->priv.webview);
+
+  g_object_set_data(view, "webview", w);
+  g_object_set_data(view, "webview-uri-scheme-fn", (gpointer)fn);
+#else
+  (void)w;
+  (void)fn;
+#endif
+}
+
+/* Loads url. The navigation functions below return -1 where the platform has
+ * no native equivalent. */
+WEBVIEW_API int webview_navigate(struct webview *w, const char *url) {
+#if defined(WEBVIEW_GTK)
+  webkit_web_view_load_uri(WEBKIT_WEB_VIEW(w->priv.webview), url);
+  return 0;
+#else
+  (void)w;
+  (void)url;
+  return -1;
+#endif
+}
+
+WEBVIEW_API int webview_reload(struct webview *w) {
+#if defined(WEBVIEW_GTK)
+  webkit_web_view_reload(WEBKIT_WEB_VIEW(w->priv.webview));
+  return 0;
+#else
+  (void)w;
+  return -1;
+#endif
+}
+
+WEBVIEW_API int webview_go_back(struct webview *w) {
+#if defined(WEBVIEW_GTK)
+  webkit_web_view_go_back(WEBKIT_WEB_VIEW(w->priv.webview));
+  return 0;
+#else
+  (void)w;
+  return -1;
+#endif
+}
+
+WEBVIEW_API int webview_go_forward(struct webview *w) {
+#if defined(WEBVIEW_GTK)
+  webkit_web_view_go_forward(WEBKIT_WEB_VIEW(w->priv.webview));
+  return 0;
+#else
+  (void)w;
+  return -1;
+#endif
+}
+
+WEBVIEW_API int webview_stop_loading(struct webview *w) {
+#if defined(WEBVIEW_GTK)
+  webkit_web_view_stop_loading(WEBKIT_WEB_VIEW(w->priv.webview));
+  return 0;
+#else
+  (void)w;
+  return -1;
+#endif
+}
+
+typedef void (*webview_load_fn)(struct webview *w, int event, const char *uri,
+                                const char *error);
+
+#if defined(WEBVIEW_GTK)
+static void webview_load_changed_cb(WebKitWebView *view, WebKitLoadEvent event,
+                                    gpointer arg) {
+  webview_load_fn fn =
+      (webview_load_fn)g_object_get_data(G_OBJECT(view), "webview-load-fn");
+
+  fn((struct webview *)arg, (int)event, webkit_web_view_get_uri(view), NULL);
+}
+
+static gboolean webview_load_failed_cb(WebKitWebView *view,
+                                       WebKitLoadEvent event, gchar *uri,
+                                       GError *error, gpointer arg) {
+  webview_load_fn fn =
+      (webview_load_fn)g_object_get_data(G_OBJECT(view), "webview-load-fn");
+
+  (void)event;
+  fn((struct webview *)arg, 4, uri, error->message);
+  return FALSE;
+}
+#endif
+
+/* Calls fn as pages load, with event 0 once loading has started, 1 when it has
+ * been redirected, 2 once the page has been committed, 3 once it has finished
+ * and 4 if it has failed, followed by 3. Returns -1 where the platform does
+ * not report page loads. */
+WEBVIEW_API int webview_set_load_handler(struct webview *w,
+                                         webview_load_fn fn) {
+#if defined(WEBVIEW_GTK)
+  GObject *view = G_OBJECT(w->priv.webview);
+
+  g_object_set_data(view, "webview-load-fn", (gpointer)fn);
+  g_signal_connect(view, "load-changed", G_CALLBACK(webview_load_changed_cb),
+                   w);
+  g_signal_connect(view, "load-failed", G_CALLBACK(webview_load_failed_cb), w);
+  return 0;
+#else
+  (void)w;
+  (void)fn;
+  return -1;
+#endif
+}
+
+typedef int (*webview_navigation_fn)(struct webview *w, const char *uri,
+                                     int kind);
+
+#if defined(WEBVIEW_GTK)
+#if WEBKIT_CHECK_VERSION(2, 40, 0)
+/* Whether WebKit loads uri itself, rather than handing it to other apps. */
+static int webview_loads_uri(WebKitWebView *view, const char *uri) {
+  static const char *const schemes[] = {"http", "https", "file",
+                                        "about", "data",  "blob"};
+  WebKitSecurityManager *security =
+      webkit_web_context_get_security_manager(webkit_web_view_get_context(view));
+  gchar *scheme = g_uri_parse_scheme(uri);
+  int loads = 0;
+  size_t i;
+
+  if (scheme == NULL) {
+    return 0;
+  }
+  for (i = 0; i < G_N_ELEMENTS(schemes); i++) {
+    loads = loads || g_ascii_strcasecmp(scheme, schemes[i]) == 0;
+  }
+  /* Custom schemes are registered as secure */
+  loads = loads || webkit_security_manager_uri_scheme_is_secure(security,
+                                                                scheme);
+  g_free(scheme);
+  return loads;
+}
+#endif
+
+/* Whether uri may be opened in other apps: those of other schemes could be
+ * run with arguments chosen by the page. */
+static int webview_opens_uri(const char *uri) {
+  gchar *scheme = g_uri_parse_scheme(uri);
+  int opens = scheme != NULL && (g_ascii_strcasecmp(scheme, "http") == 0 ||
+                                 g_ascii_strcasecmp(scheme, "https") == 0 ||
+                                 g_ascii_strcasecmp(scheme, "mailto") == 0);
+
+  g_free(scheme);
+  return opens;
+}
+
+/* Calls the navigation handler of view, and carries out its decision. Returns
+ * FALSE to leave an allowed response to WebKit. */
+static gboolean webview_decide_navigation(WebKitWebView *view,
+                                          WebKitPolicyDecision *decision,
+                                          WebKitPolicyDecisionType type,
+                                          struct webview *w, const char *uri,
+                                          int kind) {
+  webview_navigation_fn fn = (webview_navigation_fn)g_object_get_data(
+      G_OBJECT(view), "webview-navigation-fn");
+
+  switch (fn(w, uri, kind)) {
+  case 0:
+    if (type == WEBKIT_POLICY_DECISION_TYPE_NEW_WINDOW_ACTION) {
+      /* There is no other window to open, so load it in this one */
+      webkit_policy_decision_ignore(decision);
+      webkit_web_view_load_uri(view, uri);
+    } else if (type == WEBKIT_POLICY_DECISION_TYPE_RESPONSE) {
+      return FALSE;
+    } else {
+      webkit_policy_decision_use(decision);
+    }
+    break;
+  case 2:
+    webkit_policy_decision_ignore(decision);
+    if (webview_opens_uri(uri)) {
+      g_app_info_launch_default_for_uri(uri, NULL, NULL);
+    }
+    break;
+  default:
+    webkit_policy_decision_ignore(decision);
+    break;
+  }
+  return TRUE;
+}
+
+static gboolean webview_decide_policy_cb(WebKitWebView *view,
+                                         WebKitPolicyDecision *decision,
+                                         WebKitPolicyDecisionType type,
+                                         gpointer arg) {
+#if WEBKIT_CHECK_VERSION(2, 40, 0)
+  GHashTable *kinds = (GHashTable *)g_object_get_data(
+      G_OBJECT(view), "webview-navigation-kinds");
+  gpointer value;
+#endif
+  WebKitNavigationAction *action;
+  WebKitURIRequest *request;
+  gchar *uri;
+  int kind;
+  gboolean handled;
+
+  switch (type) {
+  case WEBKIT_POLICY_DECISION_TYPE_NAVIGATION_ACTION:
+  case WEBKIT_POLICY_DECISION_TYPE_NEW_WINDOW_ACTION:
+    action = webkit_navigation_policy_decision_get_navigation_action(
+        WEBKIT_NAVIGATION_POLICY_DECISION(decision));
+    request = webkit_navigation_action_get_request(action);
+    kind = type == WEBKIT_POLICY_DECISION_TYPE_NEW_WINDOW_ACTION
+               ? 6
+               : (int)webkit_navigation_action_get_navigation_type(action);
+#if WEBKIT_CHECK_VERSION(2, 40, 0)
+    /* Navigation actions do not tell which frame they load, so pages WebKit
+     * loads itself are decided once answered, which does. Pages restored from
+     * history may not be requested again, and are decided now. */
+    if (type == WEBKIT_POLICY_DECISION_TYPE_NAVIGATION_ACTION &&
+        kind != WEBKIT_NAVIGATION_TYPE_BACK_FORWARD &&
+        webview_loads_uri(view, webkit_uri_request_get_uri(request))) {
+      g_hash_table_insert(kinds, g_strdup(webkit_uri_request_get_uri(request)),
+                          GINT_TO_POINTER(kind));
+      return FALSE;
+    }
+#endif
+    break;
+#if WEBKIT_CHECK_VERSION(2, 40, 0)
+  case WEBKIT_POLICY_DECISION_TYPE_RESPONSE:
+    request = webkit_response_policy_decision_get_request(
+        WEBKIT_RESPONSE_POLICY_DECISION(decision));
+    /* Redirected navigations are answered for another URI */
+    kind = WEBKIT_NAVIGATION_TYPE_OTHER;
+    if (g_hash_table_lookup_extended(kinds,
+                                     webkit_uri_request_get_uri(request),
+                                     NULL, &value)) {
+      kind = GPOINTER_TO_INT(value);
+      g_hash_table_remove(kinds, webkit_uri_request_get_uri(request));
+    }
+    if (!webkit_response_policy_decision_is_main_frame_main_resource(
+            WEBKIT_RESPONSE_POLICY_DECISION(decision))) {
+      return FALSE;
+    }
+    break;
+#endif
+  default:
+    return FALSE;
+  }
+
+  uri = g_strdup(webkit_uri_request_get_uri(request));
+  handled = webview_decide_navigation(view, decision, type,
+                                      (struct webview *)arg, uri, kind);
+  g_free(uri);
+  return handled;
+}
+#endif
+
+/* Calls fn to decide whether navigating the main frame to uri is allowed,
+ * with kind the WebKitNavigationType of the navigation, or 6 if it asks for a
+ * new window. With WebKitGTK 2.40 and later, pages WebKit loads itself are
+ * decided once requested, as only then frames are told apart; earlier, those of
+ * other frames are decided too. fn returns 0 to allow it, 1 to block it and 2
+ * to open uri in the default browser instead, which only http, https and
+ * mailto URIs are. Returns -1 where navigations cannot be intercepted. */
+WEBVIEW_API int webview_set_navigation_handler(struct webview *w,
+                                               webview_navigation_fn fn) {
+#if defined(WEBVIEW_GTK)
+  GObject *view = G_OBJECT(w->priv.webview);
+
+  g_object_set_data(view, "webview-navigation-fn", (gpointer)fn);
+  g_object_set_data_full(
+      view, "webview-navigation-kinds",
+      g_hash_table_new_full(g_str_hash, g_str_equal, g_free, NULL),
+      (GDestroyNotify)g_hash_table_destroy);
+  g_signal_connect(view, "decide-policy", G_CALLBACK(webview_decide_policy_cb),
+                   w);
+  return 0;
+#else
+  (void)w;
+  (void)fn;
+  return -1;
+#endif
+}
+
+/* Applies the settings of the view. user_agent is left as is if NULL, and font
+ * sizes if 0. Returns -1 where they cannot be changed. */
+WEBVIEW_API int webview_apply_settings(struct webview *w,
+                                       const char *user_agent, int javascript,
+                                       int local_storage, int webgl,
+                                       int autoplay, int default_font_size,
+                                       int monospace_font_size,
+                                       int file_access) {
+#if defined(WEBVIEW_GTK)
+  WebKitSettings *settings =
+      webkit_web_view_get_settings(WEBKIT_WEB_VIEW(w->priv.webview));
+
+  if (user_agent != NULL) {
+    webkit_settings_set_user_agent(settings, user_agent);
+  }
+#if WEBKIT_CHECK_VERSION(2, 24, 0)
+  /* Only page scripts, leaving user scripts and evaluated ones running */
+  webkit_settings_set_enable_javascript_markup(settings, javascript);
+#else
+  webkit_settings_set_enable_javascript(settings, javascript);
+#endif
+  webkit_settings_set_enable_html5_local_storage(settings, local_storage);
+  webkit_settings_set_enable_webgl(settings, webgl);
+  webkit_settings_set_media_playback_requires_user_gesture(settings,
+                                                           !autoplay);
+  if (default_font_size > 0) {
+    webkit_settings_set_default_font_size(settings, default_font_size);
+  }
+  if (monospace_font_size > 0) {
+    webkit_settings_set_default_monospace_font_size(settings,
+                                                    monospace_font_size);
+  }
+  webkit_settings_set_allow_file_access_from_file_urls(settings, file_access);
+  return 0;
+#else
+  (void)w;
+  (void)user_agent;
+  (void)javascript;
+  (void)local_storage;
+  (void)webgl;
+  (void)autoplay;
+  (void)default_font_size;
+  (void)monospace_font_size;
+  (void)file_access;
+  return -1;
+#endif
+}